@@ -0,0 +1,335 @@
+//! Content-hashed asset names. A static build copies `assets/app.css` to
+//! `assets/app.3f9c1a2b.css` and points every page at the new name, so a
+//! changed file gets a new URL and browsers and CDNs may cache each one
+//! forever. The mapping is written to `assets-manifest.json`, and `_headers`
+//! (Netlify, Cloudflare Pages) marks the hashed files immutable; `serve`
+//! sends the same header.
+//!
+//! `serve --watch` keeps the plain names, so a stylesheet edit can be swapped
+//! into open pages without re-rendering them.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use crate::output::{Output, SiteFiles};
+
+/// The `Cache-Control` value for fingerprinted files.
+pub(crate) const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+pub(crate) const MANIFEST: &str = "assets-manifest.json";
+
+/// Asset paths relative to `assets/`, original → fingerprinted. Empty when
+/// assets keep their names.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct AssetManifest {
+    base_path: String,
+    fingerprinted: bool,
+    names: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Assets copied under their own names.
+    pub(crate) fn plain(base_path: &str) -> Self {
+        Self {
+            base_path: base_path.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn is_fingerprinted(&self) -> bool {
+        self.fingerprinted
+    }
+
+    /// `{base}/assets/app.css` → `{base}/assets/app.3f9c1a2b.css`. Anything
+    /// else, including a full URL to another host, is returned unchanged.
+    pub(crate) fn url(&self, url: &str) -> String {
+        let prefix = format!("{}/assets/", self.base_path);
+        url.strip_prefix(&prefix)
+            .and_then(|rel| self.names.get(rel))
+            .map_or_else(|| url.to_string(), |hashed| format!("{prefix}{hashed}"))
+    }
+
+    /// Point every `{base}/assets/…` reference in a rendered page at the
+    /// fingerprinted file. Tera escapes `/` in `{{ base_path }}`, so the
+    /// escaped form of the base path is recognised too. A reference has to
+    /// start an attribute value or `url(`, so `https://cdn/assets/app.css`
+    /// is left alone.
+    pub(crate) fn rewrite(&self, html: &str) -> String {
+        if self.names.is_empty() {
+            return html.to_string();
+        }
+        let escaped_base = self.base_path.replace('/', "&#x2F;");
+        let mut out = String::with_capacity(html.len());
+        let mut done = 0;
+        let mut search = 0;
+        while let Some(found) = html[search..].find("/assets/") {
+            let at = search + found;
+            let rel_start = at + "/assets/".len();
+            search = rel_start;
+
+            let head = &html[done..at];
+            let base_len = if head.ends_with(&self.base_path) {
+                self.base_path.len()
+            } else if head.ends_with(&escaped_base) {
+                escaped_base.len()
+            } else {
+                continue;
+            };
+            let starts_value = html[..at - base_len]
+                .chars()
+                .next_back()
+                .is_none_or(|c| matches!(c, '"' | '\'' | '(' | '=') || c.is_whitespace());
+            if !starts_value {
+                continue;
+            }
+            let rel_len = html[rel_start..]
+                .find(|c: char| {
+                    matches!(c, '"' | '\'' | ')' | '?' | '#' | '<' | '>') || c.is_whitespace()
+                })
+                .unwrap_or(html.len() - rel_start);
+            let rel = &html[rel_start..rel_start + rel_len];
+            if let Some(hashed) = self.names.get(rel) {
+                out.push_str(&html[done..rel_start]);
+                out.push_str(hashed);
+                done = rel_start + rel_len;
+                search = done;
+            }
+        }
+        out.push_str(&html[done..]);
+        out
+    }
+
+    /// `assets-manifest.json` and `_headers`, for a fingerprinted build.
+    pub(crate) fn write(&self, out: &Output) -> Result<()> {
+        if !self.fingerprinted {
+            return Ok(());
+        }
+        out.write(MANIFEST, serde_json::to_string_pretty(&self.names)?)?;
+        let mut headers = String::new();
+        for hashed in self.names.values() {
+            headers.push_str(&format!(
+                "{}/assets/{hashed}\n  Cache-Control: {IMMUTABLE}\n",
+                self.base_path
+            ));
+        }
+        out.write("_headers", headers)
+    }
+}
+
+/// The fingerprinted files an `assets-manifest.json` lists, relative to
+/// `assets/`. Empty without a manifest.
+pub(crate) fn hashed_paths(manifest: Option<&[u8]>) -> HashSet<String> {
+    manifest
+        .and_then(|json| serde_json::from_slice::<BTreeMap<String, String>>(json).ok())
+        .map(|names| names.into_values().collect())
+        .unwrap_or_default()
+}
+
+/// Whether a request path is one of the published build's fingerprinted
+/// files, which `serve` marks immutable as `_headers` does.
+pub(crate) fn is_immutable(files: &SiteFiles, base_path: &str, path: &str) -> bool {
+    path.strip_prefix(base_path)
+        .and_then(|path| path.strip_prefix("/assets/"))
+        .is_some_and(|rel| files.is_hashed_asset(rel))
+}
+
+/// Copy `src_dir` into `assets/` under content-hashed names. Stylesheets are
+/// hashed last, after their `url(…)` references to other assets have been
+/// rewritten, so a new image also gives the stylesheet using it a new name.
+pub(crate) fn copy_fingerprinted(
+    src_dir: &Path,
+    out: &Output,
+    base_path: &str,
+) -> Result<AssetManifest> {
+    let mut manifest = AssetManifest {
+        base_path: base_path.to_string(),
+        fingerprinted: true,
+        names: BTreeMap::new(),
+    };
+    if !src_dir.exists() {
+        return Ok(manifest);
+    }
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    for entry in WalkDir::new(src_dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let path = entry.path();
+        let rel = path
+            .strip_prefix(src_dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        let contents =
+            fs::read(path).with_context(|| format!("failed to copy {}", path.display()))?;
+        files.push((rel, contents));
+    }
+    files.sort_by_key(|(rel, _)| (rel.ends_with(".css"), rel.clone()));
+
+    for (rel, contents) in files {
+        let contents = if rel.ends_with(".css") {
+            let css = String::from_utf8_lossy(&contents);
+            rewrite_css(&css, &rel, &manifest.names).into_bytes()
+        } else {
+            contents
+        };
+        let hashed = hashed_name(&rel, &contents);
+        out.write(&format!("assets/{hashed}"), contents)?;
+        manifest.names.insert(rel, hashed);
+    }
+    Ok(manifest)
+}
+
+/// `css/app.css` + contents → `css/app.3f9c1a2b.css`.
+fn hashed_name(rel: &str, contents: &[u8]) -> String {
+    let hash = content_hash(contents);
+    let (dir, file) = rel.rsplit_once('/').map_or(("", rel), |(d, f)| (d, f));
+    let file = match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}.{hash}.{ext}"),
+        _ => format!("{file}.{hash}"),
+    };
+    match dir {
+        "" => file,
+        dir => format!("{dir}/{file}"),
+    }
+}
+
+/// 64-bit FNV-1a, cut to eight hex digits: stable across Rust versions and
+/// platforms, which `DefaultHasher` does not promise.
+fn content_hash(contents: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in contents {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:08x}", hash >> 32)
+}
+
+/// Rewrite `url(fonts/a.woff2)` in the stylesheet at `css_rel` to the
+/// fingerprinted name. Only paths relative to the stylesheet, at or below
+/// its folder, are followed.
+fn rewrite_css(css: &str, css_rel: &str, names: &BTreeMap<String, String>) -> String {
+    let css_dir = css_rel.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(at) = rest.find("url(") {
+        let (before, after) = rest.split_at(at + "url(".len());
+        out.push_str(before);
+        let Some(close) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let inner = &after[..close];
+        let quote = inner
+            .trim()
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''));
+        let target = inner.trim().trim_matches(|c| matches!(c, '"' | '\''));
+        let (path, suffix) = target
+            .find(['?', '#'])
+            .map_or((target, ""), |idx| target.split_at(idx));
+        let local = !path.is_empty()
+            && !path.contains(':')
+            && !path.starts_with('/')
+            && !path.split('/').any(|seg| seg == "..");
+        let full = match css_dir {
+            "" => path.to_string(),
+            dir => format!("{dir}/{path}"),
+        };
+        match names.get(&full).filter(|_| local) {
+            Some(hashed) => {
+                let file = hashed.rsplit_once('/').map_or(hashed.as_str(), |(_, f)| f);
+                let dir = path.rsplit_once('/').map_or("", |(d, _)| d);
+                let new_path = match dir {
+                    "" => file.to_string(),
+                    dir => format!("{dir}/{file}"),
+                };
+                let q = quote.map(String::from).unwrap_or_default();
+                out.push_str(&format!("{q}{new_path}{suffix}{q}"));
+            }
+            None => out.push_str(inner),
+        }
+        rest = &after[close..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::TempDir;
+    use std::sync::Mutex;
+
+    #[test]
+    fn assets_get_hashed_names_and_pages_follow() {
+        let root = TempDir::new(
+            "assets",
+            &[
+                ("fonts/inter.woff2", "font"),
+                (
+                    "app.css",
+                    "@font-face{src:url(\"fonts/inter.woff2?v=1\")}body{background:url(https://x/y.png)}",
+                ),
+            ],
+        );
+
+        let out = Output::Memory(Mutex::default());
+        let manifest = copy_fingerprinted(&root, &out, "/docs").unwrap();
+        let font = manifest.names["fonts/inter.woff2"].clone();
+        let css = manifest.names["app.css"].clone();
+        assert!(font.starts_with("fonts/inter.") && font.ends_with(".woff2"));
+        assert_eq!(css.len(), "app.12345678.css".len());
+        let font_file = font.trim_start_matches("fonts/");
+        let written = out.read(&format!("assets/{css}")).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&written),
+            format!(
+                "@font-face{{src:url(\"fonts/{font_file}?v=1\")}}body{{background:url(https://x/y.png)}}"
+            )
+        );
+
+        let html = concat!(
+            r#"<link href="&#x2F;docs/assets/app.css"><img src="/docs/assets/logo.png">"#,
+            r#"<a href="https://cdn.example.com/docs/assets/app.css">"#,
+        );
+        assert_eq!(
+            manifest.rewrite(html),
+            format!(
+                r#"<link href="&#x2F;docs/assets/{css}"><img src="/docs/assets/logo.png"><a href="https://cdn.example.com/docs/assets/app.css">"#
+            )
+        );
+        assert_eq!(
+            manifest.url("/docs/assets/app.css"),
+            format!("/docs/assets/{css}")
+        );
+        assert_eq!(manifest.url("https://x/logo.png"), "https://x/logo.png");
+
+        manifest.write(&out).unwrap();
+        let headers = String::from_utf8_lossy(&out.read("_headers").unwrap()).into_owned();
+        assert!(headers.contains(&format!(
+            "/docs/assets/{css}\n  Cache-Control: {IMMUTABLE}\n"
+        )));
+
+        let files = SiteFiles::memory();
+        assert!(!is_immutable(
+            &files,
+            "/docs",
+            &format!("/docs/assets/{css}")
+        ));
+        files.publish(out);
+        assert!(is_immutable(
+            &files,
+            "/docs",
+            &format!("/docs/assets/{css}")
+        ));
+        assert!(!is_immutable(&files, "/docs", "/docs/assets/app.css"));
+    }
+}
@@ -0,0 +1,337 @@
+//! `docsgen check`: run the build pipeline up to the point where HTML would be
+//! rendered and report what is wrong with the docs tree instead. Nothing is
+//! written, so it is safe to run as a CI gate before publishing.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::links::{MdLinks, PageLinks, SourceUrls, check_links};
+use crate::{
+    BuildArgs, DEFAULT_API_BASE, PageMeta, PageState, SITE_KEYS, build_api_endpoints,
+    collect_site_meta, expand_includes, markdown_to_html, markdown_to_html_with_toc, parse_nav,
+    resolve_base_path, resolve_nav_target, site_config_lines, split_frontmatter, split_sections,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// One problem found in the docs tree. `code` is a stable identifier CI
+/// scripts can filter on; `message` is for people.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    pub(crate) code: &'static str,
+    pub(crate) file: String,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn error(
+        code: &'static str,
+        file: &Path,
+        line: Option<usize>,
+        message: String,
+    ) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            file: file.display().to_string(),
+            line,
+            message,
+        }
+    }
+
+    pub(crate) fn warning(
+        code: &'static str,
+        file: &Path,
+        line: Option<usize>,
+        message: String,
+    ) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, file, line, message)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: ", self.file)?,
+            None => write!(f, "{}: ", self.file)?,
+        }
+        write!(f, "{severity}[{}]: {}", self.code, self.message)
+    }
+}
+
+pub(crate) fn check_site(args: &BuildArgs) -> Result<Vec<Diagnostic>> {
+    if !args.docs_dir.exists() {
+        return Err(anyhow!("docs dir not found: {}", args.docs_dir.display()));
+    }
+
+    let site = collect_site_meta(&args.docs_dir, args.drafts, &resolve_base_path(args))?;
+    let mut diagnostics = Vec::new();
+    let mut page_links = Vec::new();
+    let source_urls = SourceUrls::new(&site);
+
+    check_site_config(&args.docs_dir.join("site.md"), &mut diagnostics);
+    for lang in &site.langs {
+        let lang_dir = args.docs_dir.join(&lang.code);
+        check_site_config(&lang_dir.join("site.md"), &mut diagnostics);
+
+        let nav_path = lang_dir.join("nav.md");
+        if let Ok(content) = fs::read_to_string(&nav_path) {
+            let entries = parse_nav(&content);
+            for entry in &entries {
+                if resolve_nav_target(&lang.pages, &entry.target).is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        "nav-unresolved",
+                        &nav_path,
+                        Some(entry.line),
+                        format!("nav entry '{}' does not match any page", entry.target),
+                    ));
+                }
+            }
+            let listable = lang
+                .pages
+                .iter()
+                .filter(|p| p.state != PageState::Hidden && p.fallback_from.is_none());
+            for page in listable {
+                let listed = entries.iter().any(|entry| {
+                    resolve_nav_target(std::slice::from_ref(page), &entry.target).is_some()
+                });
+                if !listed {
+                    diagnostics.push(Diagnostic::warning(
+                        "nav-missing",
+                        &nav_path,
+                        None,
+                        format!("page '{}' is not listed in nav.md", page.source_rel),
+                    ));
+                }
+            }
+        }
+
+        for page in &lang.pages {
+            let source_lang = page.fallback_from.as_deref().unwrap_or(&lang.code);
+            let md_path = args.docs_dir.join(source_lang).join(&page.source_rel);
+            let Ok(markdown) = fs::read_to_string(&md_path) else {
+                continue;
+            };
+            let base_dir = md_path.parent().unwrap_or(&lang_dir);
+            // A fallback stand-in's problems are already reported against the
+            // default language's page; it is only needed as a link target.
+            let mut page_diagnostics = Vec::new();
+            let links = check_page(
+                &source_urls,
+                &lang.code,
+                page,
+                &md_path,
+                base_dir,
+                &markdown,
+                &mut page_diagnostics,
+            );
+            if page.fallback_from.is_none() {
+                diagnostics.extend(page_diagnostics);
+            }
+            page_links.push(links);
+        }
+    }
+
+    // Dead links are warnings unless the build would refuse them.
+    for mut diagnostic in check_links(&page_links, &site.base_path) {
+        if args.deny_broken_links {
+            diagnostic.severity = Severity::Error;
+        }
+        diagnostics.push(diagnostic);
+    }
+
+    Ok(diagnostics)
+}
+
+fn check_site_config(path: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let Ok(content) = fs::read_to_string(path) else {
+        return;
+    };
+    for (line, key, _) in site_config_lines(&content) {
+        if !SITE_KEYS.contains(&key) {
+            diagnostics.push(Diagnostic::warning(
+                "unknown-site-key",
+                path,
+                Some(line),
+                format!("unknown key '{key}' (known: {})", SITE_KEYS.join(", ")),
+            ));
+        }
+    }
+}
+
+fn check_page(
+    urls: &SourceUrls,
+    lang: &str,
+    page: &PageMeta,
+    md_path: &Path,
+    base_dir: &Path,
+    markdown: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> PageLinks {
+    // Expanding each directive on its own pins a failure to its line, and
+    // still follows nested includes the way the build does.
+    for (idx, line) in markdown.lines().enumerate() {
+        if line.trim().starts_with("@include:")
+            && let Err(err) = expand_includes(line, base_dir)
+        {
+            diagnostics.push(Diagnostic::error(
+                "include",
+                md_path,
+                Some(idx + 1),
+                format!("{err:#}"),
+            ));
+        }
+    }
+
+    for (line, code) in json_fences(markdown) {
+        if let Err(err) = serde_json::from_str::<serde_json::Value>(&code) {
+            diagnostics.push(Diagnostic::error(
+                "invalid-json",
+                md_path,
+                Some(line),
+                format!("JSON block does not parse: {err}"),
+            ));
+        }
+    }
+
+    let body = split_frontmatter(markdown)
+        .map(|(_, body)| body)
+        .unwrap_or(markdown);
+    let expanded = expand_includes(body, base_dir).unwrap_or_else(|_| body.to_string());
+    let (desc_md, arch_md, json_md, text_md) = split_sections(&expanded);
+    let endpoints = build_api_endpoints(&json_md, DEFAULT_API_BASE, &[]);
+    for block in endpoints.iter().flat_map(|endpoint| &endpoint.blocks) {
+        if block.kind == "request" && block.path.is_none() {
+            diagnostics.push(Diagnostic::warning(
+                "request-without-path",
+                md_path,
+                None,
+                format!(
+                    "request block '{}' has no path, so no code samples are generated",
+                    block.label
+                ),
+            ));
+        }
+    }
+
+    let md_links = MdLinks::new(urls, lang, &page.source_rel);
+    let (description_html, _) = markdown_to_html_with_toc(&desc_md, Some(&md_links));
+    let fragments = [
+        description_html,
+        markdown_to_html(&arch_md, Some(&md_links)),
+        markdown_to_html(&json_md, Some(&md_links)),
+        markdown_to_html(&text_md, Some(&md_links)),
+    ];
+    diagnostics.extend(md_links.diagnostics(md_path, markdown));
+    let fragments: Vec<&str> = fragments.iter().map(String::as_str).collect();
+    let anchors: Vec<String> = endpoints
+        .into_iter()
+        .filter_map(|endpoint| endpoint.id)
+        .collect();
+    PageLinks::new(&page.url, md_path, markdown, &fragments, &anchors)
+}
+
+/// Every ```` ```json ```` fence with the line its opening marker sits on.
+fn json_fences(md: &str) -> Vec<(usize, String)> {
+    let mut fences = Vec::new();
+    let mut open: Option<(String, usize, bool, Vec<&str>)> = None;
+
+    for (idx, line) in md.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some((marker, start, is_json, lines)) = open.as_mut() {
+            if trimmed.starts_with(marker.as_str()) && trimmed.trim_end_matches('`').is_empty() {
+                if *is_json {
+                    fences.push((*start, lines.join("\n")));
+                }
+                open = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            let marker: String = trimmed.chars().take_while(|c| *c == '`').collect();
+            let lang = trimmed[marker.len()..].trim().to_lowercase();
+            open = Some((marker, idx + 1, lang == "json", Vec::new()));
+        }
+    }
+
+    fences
+}
+
+pub(crate) fn report(diagnostics: &[Diagnostic], format: ReportFormat) -> Result<()> {
+    match format {
+        ReportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(diagnostics)?);
+        }
+        ReportFormat::Text => {
+            for d in diagnostics {
+                println!("{d}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::TempDir;
+
+    fn args_for(docs_dir: &Path) -> BuildArgs {
+        use clap::Parser;
+        BuildArgs::parse_from(["build", "--docs-dir", docs_dir.to_str().unwrap()])
+    }
+
+    #[test]
+    fn reports_each_kind_of_problem_with_its_line() {
+        let root = TempDir::new(
+            "check",
+            &[
+                ("site.md", "title: X\ntheem: ocean\n"),
+                ("en/nav.md", "[Start]\n- welcome.md\n- gone.md\n"),
+                ("en/welcome.md", "# Welcome\n\n@include: missing.md\n"),
+                (
+                    "en/api.md",
+                    "# Api\n\nSee [errors](/en/eror).\n\n## Architecture\n\n### JSON\n\n#### Request\n\n```json\n{ \"method\": \"GET\", }\n```\n",
+                ),
+            ],
+        );
+        let diagnostics = check_site(&args_for(&root)).unwrap();
+        let found: Vec<(&str, Option<usize>)> =
+            diagnostics.iter().map(|d| (d.code, d.line)).collect();
+
+        assert!(found.contains(&("unknown-site-key", Some(2))));
+        assert!(found.contains(&("nav-unresolved", Some(3))));
+        assert!(found.contains(&("nav-missing", None)));
+        assert!(found.contains(&("include", Some(3))));
+        assert!(found.contains(&("invalid-json", Some(11))));
+        assert!(found.contains(&("broken-link", Some(3))));
+        assert!(found.contains(&("request-without-path", None)));
+    }
+}
@@ -0,0 +1,49 @@
+//! Scratch directories for tests, removed when the test is done with them.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A directory under the system temp dir, removed on drop. Derefs to its
+/// path.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `docsgen-{name}-{pid}-{n}`, holding `files` (relative path, content).
+    /// The counter gives every call its own directory, so a test that runs
+    /// twice in one process never meets its own leftovers.
+    pub(crate) fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let root = std::env::temp_dir().join(format!("docsgen-{name}-{}-{n}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let dir = Self(root);
+        for (rel, content) in files {
+            dir.write(rel, content);
+        }
+        dir
+    }
+
+    /// Write `rel`, creating its parent directories.
+    pub(crate) fn write(&self, rel: &str, content: impl AsRef<[u8]>) {
+        let path = self.0.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
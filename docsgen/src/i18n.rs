@@ -0,0 +1,403 @@
+//! `docsgen i18n-status`: compare every language against the default one,
+//! page by page (`source_rel`), and list what a translator still has to do.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Result, anyhow};
+use pulldown_cmark::{Event, Parser as MdParser, Tag};
+use serde::Serialize;
+
+use crate::check::ReportFormat;
+use crate::{
+    DEFAULT_API_BASE, PageMeta, build_api_blocks, collect_site_meta, expand_includes, md_options,
+    split_frontmatter, split_sections,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Status {
+    /// In the default language only.
+    Missing,
+    /// In this language only.
+    Extra,
+    /// The heading outline differs, so a section was added or dropped.
+    Headings,
+    /// The request blocks cover different endpoints.
+    Endpoints,
+    /// The default-language page changed after the translation last did.
+    Stale,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Missing => "missing",
+            Status::Extra => "extra",
+            Status::Headings => "headings",
+            Status::Endpoints => "endpoints",
+            Status::Stale => "stale",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct StatusEntry {
+    pub(crate) lang: String,
+    pub(crate) page: String,
+    pub(crate) status: Status,
+    pub(crate) detail: String,
+}
+
+/// What a page is compared on, read once per file.
+struct PageShape {
+    headings: Vec<u8>,
+    endpoints: BTreeSet<String>,
+}
+
+pub(crate) fn translation_status(docs_dir: &Path) -> Result<Vec<StatusEntry>> {
+    if !docs_dir.exists() {
+        return Err(anyhow!("docs dir not found: {}", docs_dir.display()));
+    }
+
+    // Drafts count: a translation in progress is still a translation.
+    let site = collect_site_meta(docs_dir, true, "")?;
+    let own_pages = |pages: &[PageMeta]| -> BTreeSet<String> {
+        pages
+            .iter()
+            .filter(|page| page.fallback_from.is_none())
+            .map(|page| page.source_rel.clone())
+            .collect()
+    };
+    let default_dir = docs_dir.join(&site.default_lang);
+    let default_pages = site
+        .langs
+        .iter()
+        .find(|lang| lang.code == site.default_lang)
+        .map(|lang| own_pages(&lang.pages))
+        .unwrap_or_default();
+
+    let mut entries = Vec::new();
+    for lang in site.langs.iter().filter(|l| l.code != site.default_lang) {
+        let lang_dir = docs_dir.join(&lang.code);
+        let pages = own_pages(&lang.pages);
+        let entry = |page: &str, status, detail: String| StatusEntry {
+            lang: lang.code.clone(),
+            page: page.to_string(),
+            status,
+            detail,
+        };
+
+        for page in default_pages.difference(&pages) {
+            entries.push(entry(page, Status::Missing, String::new()));
+        }
+        for page in pages.difference(&default_pages) {
+            let detail = format!("no {} page with this name", site.default_lang);
+            entries.push(entry(page, Status::Extra, detail));
+        }
+
+        for page in default_pages.intersection(&pages) {
+            let original_path = default_dir.join(page);
+            let translated_path = lang_dir.join(page);
+            let (Some(original), Some(translated)) =
+                (page_shape(&original_path), page_shape(&translated_path))
+            else {
+                continue;
+            };
+
+            if original.headings != translated.headings {
+                let detail = format!(
+                    "{} {}, {} {}",
+                    site.default_lang,
+                    outline(&original.headings),
+                    lang.code,
+                    outline(&translated.headings)
+                );
+                entries.push(entry(page, Status::Headings, detail));
+            }
+
+            if original.endpoints != translated.endpoints {
+                let mut parts = Vec::new();
+                let untranslated: Vec<&str> = original
+                    .endpoints
+                    .difference(&translated.endpoints)
+                    .map(String::as_str)
+                    .collect();
+                if !untranslated.is_empty() {
+                    parts.push(format!("not in {}: {}", lang.code, untranslated.join(", ")));
+                }
+                let unknown: Vec<&str> = translated
+                    .endpoints
+                    .difference(&original.endpoints)
+                    .map(String::as_str)
+                    .collect();
+                if !unknown.is_empty() {
+                    parts.push(format!(
+                        "not in {}: {}",
+                        site.default_lang,
+                        unknown.join(", ")
+                    ));
+                }
+                entries.push(entry(page, Status::Endpoints, parts.join("; ")));
+            }
+
+            if let Some((original_at, translated_at, how)) =
+                last_changed(&original_path, &translated_path)
+                && original_at > translated_at
+            {
+                let days = (original_at - translated_at) / 86_400;
+                let detail = match days {
+                    0 => format!("{} changed later the same day ({how})", site.default_lang),
+                    _ => format!("{} changed {days} day(s) later ({how})", site.default_lang),
+                };
+                entries.push(entry(page, Status::Stale, detail));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn page_shape(path: &Path) -> Option<PageShape> {
+    let source = fs::read_to_string(path).ok()?;
+    let body = split_frontmatter(&source)
+        .map(|(_, body)| body)
+        .unwrap_or(&source);
+    let base_dir = path.parent()?;
+    let expanded = expand_includes(body, base_dir).unwrap_or_else(|_| body.to_string());
+
+    // The title and section markers are translated too, and the JSON tab's
+    // `####` cards are compared as endpoints, so only the prose outline counts.
+    let (desc_md, arch_md, json_md, text_md) = split_sections(&expanded);
+    let headings = [desc_md, arch_md, text_md]
+        .iter()
+        .flat_map(|md| MdParser::new_ext(md, md_options()))
+        .filter_map(|event| match event {
+            Event::Start(Tag::Heading { level, .. }) => Some(level as u8),
+            _ => None,
+        })
+        .collect();
+    let endpoints = build_api_blocks(&json_md, DEFAULT_API_BASE, &[])
+        .into_iter()
+        .filter(|block| block.kind == "request")
+        .filter_map(|block| {
+            let path = block.path?;
+            Some(match block.method {
+                Some(method) => format!("{method} {path}"),
+                None => path,
+            })
+        })
+        .collect();
+
+    Some(PageShape {
+        headings,
+        endpoints,
+    })
+}
+
+/// `[2, 2, 3]` → `h2 h2 h3`.
+fn outline(levels: &[u8]) -> String {
+    if levels.is_empty() {
+        return "no headings".to_string();
+    }
+    levels
+        .iter()
+        .map(|level| format!("h{level}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// When a page and its translation last changed, in seconds since the
+/// epoch, and which clock said so. A checkout gives every file the same
+/// mtime, so git is asked when both files are committed without local edits;
+/// otherwise both use the mtime, as a commit time and an mtime do not compare.
+fn last_changed(original: &Path, translated: &Path) -> Option<(u64, u64, &'static str)> {
+    match (committed_at(original), committed_at(translated)) {
+        (Some(original_at), Some(translated_at)) => Some((original_at, translated_at, "git")),
+        _ => Some((mtime(original)?, mtime(translated)?, "mtime")),
+    }
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    Some(
+        fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs(),
+    )
+}
+
+/// The time of the last commit touching the file, unless it is untracked or
+/// has uncommitted changes.
+fn committed_at(path: &Path) -> Option<u64> {
+    let dir = path.parent()?;
+    let name = path.file_name()?;
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .arg("--")
+            .arg(name)
+            .current_dir(dir)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+    };
+    let clean = git(&["status", "--porcelain"]).is_some_and(|out| out.stdout.is_empty());
+    if !clean {
+        return None;
+    }
+    let out = git(&["log", "-1", "--format=%ct"])?;
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+pub(crate) fn report(entries: &[StatusEntry], format: ReportFormat) -> Result<()> {
+    match format {
+        ReportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(entries)?);
+        }
+        ReportFormat::Text => {
+            if entries.is_empty() {
+                println!("All languages match the default language.");
+                return Ok(());
+            }
+            let page_width = entries
+                .iter()
+                .map(|e| e.page.len())
+                .max()
+                .unwrap_or(0)
+                .max("PAGE".len());
+            let lang_width = entries
+                .iter()
+                .map(|e| e.lang.len())
+                .max()
+                .unwrap_or(0)
+                .max("LANG".len());
+            println!(
+                "{:lang_width$}  {:page_width$}  {:9}  DETAIL",
+                "LANG", "PAGE", "STATUS"
+            );
+            for e in entries {
+                let line = format!(
+                    "{:lang_width$}  {:page_width$}  {:9}  {}",
+                    e.lang,
+                    e.page,
+                    e.status.to_string(),
+                    e.detail
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::TempDir;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn lists_missing_extra_reshaped_and_stale_pages() {
+        let root = TempDir::new(
+            "i18n",
+            &[
+                ("en/limits.md", "# Limits\n"),
+                ("en/welcome.md", "# Welcome\n\n## Description\n\nHi.\n"),
+                ("en/errors.md", "# Errors\n\n## Description\n\n## Retries\n"),
+                (
+                    "en/users.md",
+                    "# Users\n\n## Architecture\n\n### JSON\n\n#### Request GET /v1/users\n\n```json\n{}\n```\n\n#### Request POST /v1/users\n\n```json\n{ \"name\": \"Asha\" }\n```\n",
+                ),
+                ("hi/welcome.md", "# स्वागत\n\n## Description\n\nनमस्ते।\n"),
+                ("hi/errors.md", "# त्रुटियाँ\n\n## Description\n\nसूची।\n"),
+                (
+                    "hi/users.md",
+                    "# उपयोगकर्ता\n\n## Architecture\n\n### JSON\n\n#### Request GET /v1/users\n\n```json\n{}\n```\n",
+                ),
+                ("hi/glossary.md", "# शब्दावली\n"),
+            ],
+        );
+        // The English welcome page was edited two days after its translation.
+        let now = SystemTime::now();
+        let set_mtime = |rel: &str, at: SystemTime| {
+            let file = fs::File::options()
+                .write(true)
+                .open(root.join(rel))
+                .unwrap();
+            file.set_modified(at).unwrap();
+        };
+        set_mtime("hi/welcome.md", now - Duration::from_secs(2 * 86_400));
+        set_mtime("en/welcome.md", now);
+        for rel in ["en/errors.md", "en/users.md"] {
+            set_mtime(rel, now - Duration::from_secs(3 * 86_400));
+        }
+
+        let entries = translation_status(&root).unwrap();
+        let found: Vec<(&str, Status)> = entries
+            .iter()
+            .map(|e| (e.page.as_str(), e.status))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("limits.md", Status::Missing),
+                ("glossary.md", Status::Extra),
+                ("errors.md", Status::Headings),
+                ("users.md", Status::Endpoints),
+                ("welcome.md", Status::Stale),
+            ]
+        );
+        assert_eq!(entries[2].detail, "en h2, hi no headings");
+        assert_eq!(entries[3].detail, "not in hi: POST /v1/users");
+        assert!(entries[4].detail.starts_with("en changed 2 day(s) later"));
+    }
+
+    #[test]
+    fn a_tracked_page_and_an_untracked_translation_both_use_the_mtime() {
+        let root = TempDir::new("i18n-git", &[]);
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=docs", "-c", "user.email=docs@example.com"])
+                .args(args)
+                .current_dir(&*root)
+                .env("GIT_AUTHOR_DATE", "2020-01-01T00:00:00Z")
+                .env("GIT_COMMITTER_DATE", "2020-01-01T00:00:00Z")
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        let original = root.join("welcome.md");
+        let translated = root.join("welcome.hi.md");
+        fs::write(&original, "# Welcome\n").unwrap();
+        fs::write(&translated, "# स्वागत\n").unwrap();
+        git(&["init", "-q"]);
+        git(&["add", "welcome.md"]);
+        git(&["commit", "-q", "-m", "Add welcome page"]);
+
+        // Committed in 2020 but edited since, by mtime, after the translation.
+        let now = SystemTime::now();
+        let set_mtime = |path: &Path, at: SystemTime| {
+            let file = fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(at).unwrap();
+        };
+        set_mtime(&translated, now - Duration::from_secs(2 * 86_400));
+        set_mtime(&original, now);
+
+        let (original_at, translated_at, how) = last_changed(&original, &translated).unwrap();
+        assert_eq!(how, "mtime");
+        assert_eq!((original_at - translated_at) / 86_400, 2);
+
+        git(&["add", "welcome.hi.md"]);
+        git(&["commit", "-q", "-m", "Translate welcome page"]);
+        let (original_at, translated_at, how) = last_changed(&original, &translated).unwrap();
+        assert_eq!((original_at, how), (translated_at, "git"));
+    }
+}
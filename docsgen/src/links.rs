@@ -0,0 +1,495 @@
+//! Internal link checking. Every `href` in a page's rendered markdown is
+//! resolved against the URLs of all pages in every language and the heading
+//! ids those pages generate, so a renamed page or heading shows up at build
+//! time instead of as a 404 or a dead `#fragment` on the published site.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::CowStr;
+
+use crate::SiteMeta;
+use crate::check::Diagnostic;
+
+/// Published URL of every page keyed by its source path relative to the docs
+/// dir (`en/errors.md`), so a link written against the markdown file can be
+/// pointed at the page it becomes.
+pub(crate) struct SourceUrls {
+    urls: HashMap<String, String>,
+    base_path: String,
+}
+
+impl SourceUrls {
+    pub(crate) fn new(site: &SiteMeta) -> Self {
+        let mut urls = HashMap::new();
+        for lang in &site.langs {
+            for page in &lang.pages {
+                urls.insert(
+                    format!("{}/{}", lang.code, page.source_rel),
+                    page.url.clone(),
+                );
+            }
+        }
+        Self {
+            urls,
+            base_path: site.base_path.clone(),
+        }
+    }
+}
+
+/// Rewrites `[x](errors.md)`-style links while one page renders. Links are
+/// resolved relative to the page's own file, the way an editor previewing the
+/// markdown would, and the fragment is kept. Other root-relative links
+/// (`/en/errors`, `/assets/x.png`) get the site's base path.
+pub(crate) struct MdLinks<'a> {
+    urls: &'a SourceUrls,
+    /// Source path of the page being rendered, relative to the docs dir.
+    from: String,
+    unresolved: RefCell<Vec<String>>,
+}
+
+impl<'a> MdLinks<'a> {
+    pub(crate) fn new(urls: &'a SourceUrls, lang: &str, source_rel: &str) -> Self {
+        Self {
+            urls,
+            from: format!("{lang}/{source_rel}"),
+            unresolved: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn rewrite<'e>(&self, dest: CowStr<'e>) -> CowStr<'e> {
+        let (path, fragment) = match dest.split_once('#') {
+            Some((path, frag)) => (path, Some(frag)),
+            None => (dest.as_ref(), None),
+        };
+        if path.starts_with("//") || has_scheme(path) {
+            return dest;
+        }
+        if !path.ends_with(".md") {
+            if dest.starts_with('/') && !self.urls.base_path.is_empty() {
+                return CowStr::from(crate::with_base_path(&self.urls.base_path, &dest));
+            }
+            return dest;
+        }
+
+        let source = if let Some(rooted) = path.strip_prefix('/') {
+            join_url("/", rooted)
+        } else {
+            let dir = &self.from[..self.from.rfind('/').map(|i| i + 1).unwrap_or(0)];
+            join_url(&format!("/{dir}"), path)
+        };
+        match self.urls.urls.get(source.trim_start_matches('/')) {
+            Some(url) => match fragment {
+                Some(frag) => CowStr::from(format!("{url}#{frag}")),
+                None => CowStr::from(url.clone()),
+            },
+            None => {
+                self.unresolved.borrow_mut().push(dest.to_string());
+                dest
+            }
+        }
+    }
+
+    /// One warning per `.md` link that matched no page, located in `source`.
+    pub(crate) fn diagnostics(&self, file: &Path, source: &str) -> Vec<Diagnostic> {
+        let mut seen = HashSet::new();
+        self.unresolved
+            .borrow()
+            .iter()
+            .filter(|dest| seen.insert(dest.to_string()))
+            .map(|dest| {
+                Diagnostic::warning(
+                    "unresolved-md-link",
+                    file,
+                    line_of(source, dest),
+                    format!("link '{dest}' does not match any page"),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The links and anchors of one rendered page.
+pub(crate) struct PageLinks {
+    url: String,
+    file: PathBuf,
+    /// The page's own markdown, used only to find the line a link was written on.
+    source: String,
+    hrefs: Vec<String>,
+    ids: HashSet<String>,
+}
+
+impl PageLinks {
+    /// `fragments` are the HTML pieces rendered from the page's markdown
+    /// (description, arch, JSON, text); together they hold every heading id.
+    /// `anchors` are the ids the template adds around them, such as
+    /// `endpoint-post-v1-resources` on each API endpoint group.
+    pub(crate) fn new(
+        url: &str,
+        file: &Path,
+        source: &str,
+        fragments: &[&str],
+        anchors: &[String],
+    ) -> Self {
+        let mut hrefs = Vec::new();
+        let mut ids: HashSet<String> = anchors.iter().cloned().collect();
+        for html in fragments {
+            hrefs.extend(attr_values(html, "href"));
+            ids.extend(attr_values(html, "id"));
+        }
+        Self {
+            url: url.to_string(),
+            file: file.to_path_buf(),
+            source: source.to_string(),
+            hrefs,
+            ids,
+        }
+    }
+}
+
+/// Returns one warning per dead link. The caller decides whether those fail
+/// the run (`--deny-broken-links`).
+pub(crate) fn check_links<'a>(
+    pages: impl IntoIterator<Item = &'a PageLinks>,
+    base_path: &str,
+) -> Vec<Diagnostic> {
+    let pages: Vec<&PageLinks> = pages.into_iter().collect();
+    let by_url: HashMap<String, &PageLinks> = pages
+        .iter()
+        .map(|page| (normalize_url(&page.url), *page))
+        .collect();
+    let all_urls: Vec<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+
+    let mut diagnostics = Vec::new();
+    let mut reported: HashSet<(PathBuf, String)> = HashSet::new();
+
+    for page in pages {
+        for href in &page.hrefs {
+            let Some((path, fragment)) = resolve_href(&page.url, href) else {
+                continue;
+            };
+            // The root redirect, assets and the search index are not pages.
+            let site_path = path.strip_prefix(base_path).unwrap_or(&path);
+            if site_path.is_empty()
+                || site_path == "/"
+                || site_path.starts_with("/assets/")
+                || site_path == "/search.json"
+            {
+                continue;
+            }
+
+            let problem = match by_url.get(&normalize_url(&path)) {
+                None => Some(match closest(&path, all_urls.iter().copied()) {
+                    Some(hint) => format!("no page at '{path}' (did you mean '{hint}'?)"),
+                    None => format!("no page at '{path}'"),
+                }),
+                Some(target) => fragment
+                    .filter(|frag| !frag.is_empty() && !target.ids.contains(frag))
+                    .map(
+                        |frag| match closest(&frag, target.ids.iter().map(String::as_str)) {
+                            Some(hint) => format!(
+                                "no heading '#{frag}' on '{}' (did you mean '#{hint}'?)",
+                                target.url
+                            ),
+                            None => format!("no heading '#{frag}' on '{}'", target.url),
+                        },
+                    ),
+            };
+
+            let Some(problem) = problem else {
+                continue;
+            };
+            // The same dead link written twice on a page is one mistake.
+            if !reported.insert((page.file.clone(), href.clone())) {
+                continue;
+            }
+            diagnostics.push(Diagnostic::warning(
+                "broken-link",
+                &page.file,
+                line_of(&page.source, href).or_else(|| {
+                    // Rooted links were written without the base path.
+                    let authored = href
+                        .strip_prefix(base_path)
+                        .filter(|_| !base_path.is_empty())?;
+                    line_of(&page.source, authored)
+                }),
+                format!("broken link '{href}': {problem}"),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Turn an `href` into an absolute site path plus optional fragment, or `None`
+/// when it points off-site.
+fn resolve_href(page_url: &str, href: &str) -> Option<(String, Option<String>)> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with("//") || has_scheme(href) {
+        return None;
+    }
+
+    let (rest, fragment) = match href.split_once('#') {
+        Some((rest, frag)) => (rest, Some(percent_decode(frag))),
+        None => (href, None),
+    };
+    let rest = rest.split('?').next().unwrap_or_default();
+    // A `.md` href survived rewriting because it matched no page, and
+    // `MdLinks` has already reported it.
+    if rest.ends_with(".md") {
+        return None;
+    }
+
+    let path = if rest.is_empty() {
+        page_url.to_string()
+    } else if rest.starts_with('/') {
+        rest.to_string()
+    } else {
+        let base = &page_url[..page_url.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        join_url(base, rest)
+    };
+
+    Some((percent_decode(&path), fragment))
+}
+
+fn has_scheme(href: &str) -> bool {
+    match href.find(':') {
+        Some(idx) => href[..idx]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')),
+        None => false,
+    }
+}
+
+/// Resolve `rel` against a directory URL ending in `/`, folding `.` and `..`.
+fn join_url(base: &str, rel: &str) -> String {
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for segment in rel.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+    let mut out = format!("/{}", parts.join("/"));
+    if rel.ends_with('/') && out.len() > 1 {
+        out.push('/');
+    }
+    out
+}
+
+/// `/en/` and `/en`, `/en/errors/` and `/en/errors` name the same page.
+fn normalize_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Rendered hrefs are percent-encoded; the author may have typed either form.
+fn line_of(source: &str, href: &str) -> Option<usize> {
+    let decoded = percent_decode(href);
+    source
+        .lines()
+        .position(|line| line.contains(href) || line.contains(&decoded))
+        .map(|idx| idx + 1)
+}
+
+fn closest<'a>(target: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(target, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= target.len().max(candidate.len()) / 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// Values of `name="…"` / `name='…'` attributes, with the entity escaping
+/// pulldown-cmark applies undone.
+fn attr_values(html: &str, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let needle = format!(" {name}=");
+    let mut rest = html;
+    while let Some(idx) = rest.find(&needle) {
+        rest = &rest[idx + needle.len()..];
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        let body = &rest[1..];
+        let Some(end) = body.find(quote) else {
+            break;
+        };
+        values.push(body[..end].replace("&amp;", "&").replace("&quot;", "\""));
+        rest = &body[end..];
+    }
+    values
+}
+
+/// pulldown-cmark percent-encodes non-ASCII in hrefs, while heading ids keep
+/// the raw characters (Hindi headings, for instance).
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LangMeta, PageMeta, markdown_to_html, markdown_to_html_with_toc};
+
+    fn page(url: &str, md: &str) -> PageLinks {
+        let (html, _) = markdown_to_html_with_toc(md, None);
+        PageLinks::new(url, Path::new(&format!("docs{url}.md")), md, &[&html], &[])
+    }
+
+    fn site(pages: &[(&str, &str, &str)]) -> SiteMeta {
+        let mut langs: Vec<LangMeta> = Vec::new();
+        for (lang, source_rel, url) in pages {
+            if !langs.iter().any(|l| l.code == *lang) {
+                langs.push(LangMeta {
+                    code: lang.to_string(),
+                    pages: Vec::new(),
+                });
+            }
+            let entry = langs.iter_mut().find(|l| l.code == *lang).unwrap();
+            entry.pages.push(PageMeta {
+                url: url.to_string(),
+                source_rel: source_rel.to_string(),
+                ..PageMeta::default()
+            });
+        }
+        SiteMeta {
+            default_lang: langs[0].code.clone(),
+            langs,
+            base_path: String::new(),
+        }
+    }
+
+    #[test]
+    fn md_links_point_at_published_urls_relative_to_the_linking_file() {
+        let site = site(&[
+            ("en", "welcome.md", "/en/"),
+            ("en", "authentication.md", "/en/authentication"),
+            ("en", "guides/setup.md", "/en/guides/setup"),
+            ("hi", "welcome.md", "/hi/"),
+        ]);
+        let urls = SourceUrls::new(&site);
+        let links = MdLinks::new(&urls, "en", "guides/setup.md");
+        let html = markdown_to_html(
+            "[a](../authentication.md#tokens) [b](../../hi/welcome.md) [c](setup.md) \
+             [d](https://example.com/x.md) [e](missing.md)",
+            Some(&links),
+        );
+
+        assert!(html.contains("href=\"/en/authentication#tokens\""));
+        assert!(html.contains("href=\"/hi/\""));
+        assert!(html.contains("href=\"/en/guides/setup\""));
+        assert!(html.contains("href=\"https://example.com/x.md\""));
+        let diagnostics =
+            links.diagnostics(Path::new("docs/en/guides/setup.md"), "[e](missing.md)");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(1));
+    }
+
+    #[test]
+    fn rooted_links_and_images_get_the_base_path() {
+        let mut site = site(&[("en", "errors.md", "/docs/en/errors")]);
+        site.base_path = "/docs".to_string();
+        let urls = SourceUrls::new(&site);
+        let links = MdLinks::new(&urls, "en", "welcome.md");
+        let html = markdown_to_html(
+            "[a](/en/errors) [b](errors.md) ![c](/assets/d.png)",
+            Some(&links),
+        );
+
+        assert!(html.contains("href=\"/docs/en/errors\""));
+        assert!(!html.contains("/docs/docs/"));
+        assert!(html.contains("src=\"/docs/assets/d.png\""));
+    }
+
+    #[test]
+    fn resolves_pages_and_deduplicated_heading_ids_across_languages() {
+        let pages = vec![
+            page(
+                "/en/",
+                "# Home\n\n[errors](/en/errors#setup-2)\n[hindi](/hi/)\n[self](#home)\n",
+            ),
+            page("/en/errors", "# Errors\n\n## Setup\n\n## Setup\n"),
+            page("/hi/", "# नमस्ते\n\n## परिचय\n\n[intro](#परिचय)\n"),
+        ];
+        let diagnostics = check_links(&pages, "");
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    #[test]
+    fn api_endpoint_anchors_are_link_targets() {
+        let md = "# Resources\n\n[create](#endpoint-post-v1-resources)\n";
+        let (html, _) = markdown_to_html_with_toc(md, None);
+        let anchors = ["endpoint-post-v1-resources".to_string()];
+        let pages = vec![
+            PageLinks::new(
+                "/en/resources",
+                Path::new("docs/en/resources.md"),
+                md,
+                &[&html],
+                &anchors,
+            ),
+            page(
+                "/en/",
+                "# Home\n\n[list](/en/resources#endpoint-get-v1-resources)\n",
+            ),
+        ];
+        let diagnostics = check_links(&pages, "");
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("did you mean '#endpoint-post-v1-resources'")
+        );
+    }
+
+    #[test]
+    fn reports_dead_pages_and_fragments_with_a_suggestion() {
+        let pages = vec![
+            page(
+                "/en/",
+                "# Home\n\nSee [errors](/en/erors).\n\n[x](errors#setup-3)\n",
+            ),
+            page("/en/errors", "# Errors\n\n## Setup\n\n## Setup\n"),
+        ];
+        let diagnostics = check_links(&pages, "");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert!(diagnostics[0].message.contains("did you mean '/en/errors'"));
+        assert!(diagnostics[1].message.contains("did you mean '#setup-2'"));
+    }
+}
@@ -1,21 +1,39 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::num::NonZero;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex, OnceLock};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use actix_files::Files;
-use actix_web::http::header::LOCATION;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::http::header::{HeaderValue, ACCEPT_LANGUAGE, CACHE_CONTROL, LOCATION, VARY};
+use actix_web::dev::{fn_service, Service, ServiceRequest};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
 use notify::{RecursiveMode, Result as NotifyResult, Watcher};
 use pulldown_cmark::{html, CowStr, Event, Options, Parser as MdParser, Tag, TagEnd};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tera::{Context as TeraContext, Tera};
 use walkdir::WalkDir;
 
+use crate::assets::AssetManifest;
+use crate::output::{Output, SiteFiles};
+
+mod assets;
+mod check;
+#[cfg(test)]
+mod fixture;
+mod i18n;
+mod links;
+mod openapi;
+mod output;
+mod reload;
+mod samples;
+mod seo;
+
 #[derive(Parser)]
 #[command(name = "docsgen", version, about = "Rust docs generator with Actix dev server")]
 struct Cli {
@@ -25,8 +43,16 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    Build(BuildArgs),
+    Build(BuildCommandArgs),
     Serve(ServeArgs),
+    /// Validate the docs tree without writing any output.
+    Check(CheckArgs),
+    /// List pages that are missing, extra or out of date in each language.
+    I18nStatus(I18nStatusArgs),
+    /// Write a machine-readable description of the docs.
+    Export(ExportArgs),
+    /// Write docs pages from a machine-readable description.
+    Import(ImportArgs),
 }
 
 #[derive(Parser, Clone)]
@@ -45,6 +71,39 @@ struct BuildArgs {
 
     #[arg(long, default_value = "Docs")]
     site_title: String,
+
+    /// Fail the build when an internal link or `#fragment` does not resolve.
+    #[arg(long, default_value_t = false)]
+    deny_broken_links: bool,
+
+    /// Render draft pages too. Always on for `serve`.
+    #[arg(long, default_value_t = false)]
+    drafts: bool,
+
+    /// Path the site is hosted under, e.g. `/docs` or `https://org.github.io/arch/`.
+    /// Overrides `base_path:` in site.md.
+    #[arg(long)]
+    base_path: Option<String>,
+
+    /// Pages rendered at once. Defaults to the number of CPUs.
+    #[arg(long, short = 'j')]
+    jobs: Option<usize>,
+
+    /// Replace `--out-dir` even if docsgen did not create it, removing
+    /// everything in it.
+    #[arg(long, default_value_t = false)]
+    force: bool,
+}
+
+#[derive(Parser, Clone)]
+struct BuildCommandArgs {
+    /// Keep running and rebuild `--out-dir` whenever the docs, templates or
+    /// assets change.
+    #[arg(long, default_value_t = false)]
+    watch: bool,
+
+    #[command(flatten)]
+    build: BuildArgs,
 }
 
 #[derive(Parser, Clone)]
@@ -58,16 +117,162 @@ struct ServeArgs {
     #[arg(long, default_value_t = false)]
     watch: bool,
 
+    /// Write the site to `--out-dir` as `build` does, instead of serving it
+    /// from memory.
+    #[arg(long, default_value_t = false)]
+    write: bool,
+
     #[command(flatten)]
     build: BuildArgs,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Parser, Clone)]
+struct CheckArgs {
+    /// Fail on warnings as well as errors.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    #[arg(long, value_enum, default_value_t = check::ReportFormat::Text)]
+    format: check::ReportFormat,
+
+    #[command(flatten)]
+    build: BuildArgs,
+}
+
+#[derive(Parser, Clone)]
+struct I18nStatusArgs {
+    #[arg(long, default_value = "docs")]
+    docs_dir: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = check::ReportFormat::Text)]
+    format: check::ReportFormat,
+}
+
+#[derive(Parser, Clone)]
+struct ExportArgs {
+    #[command(subcommand)]
+    what: ExportCommand,
+}
+
+#[derive(Subcommand, Clone)]
+enum ExportCommand {
+    /// An OpenAPI 3.1 document built from the request and response cards.
+    Openapi(OpenapiExportArgs),
+}
+
+#[derive(Parser, Clone)]
+struct OpenapiExportArgs {
+    #[arg(long, default_value = "docs")]
+    docs_dir: PathBuf,
+
+    /// Language whose pages are read. Defaults to the site's default.
+    #[arg(long)]
+    lang: Option<String>,
+
+    /// File to write. Defaults to stdout.
+    #[arg(long, short = 'o')]
+    out: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = openapi::Format::Json)]
+    format: openapi::Format,
+
+    /// `info.version` of the document.
+    #[arg(long, default_value = "1.0.0")]
+    api_version: String,
+
+    /// Include draft pages.
+    #[arg(long, default_value_t = false)]
+    drafts: bool,
+}
+
+#[derive(Parser, Clone)]
+struct ImportArgs {
+    #[command(subcommand)]
+    what: ImportCommand,
+}
+
+#[derive(Subcommand, Clone)]
+enum ImportCommand {
+    /// Pages with request and response cards for every operation in an
+    /// OpenAPI 3 spec (JSON or YAML).
+    Openapi(OpenapiImportArgs),
+}
+
+#[derive(Parser, Clone)]
+struct OpenapiImportArgs {
+    spec: PathBuf,
+
+    #[arg(long, default_value = "docs")]
+    docs_dir: PathBuf,
+
+    #[arg(long, default_value = "en")]
+    lang: String,
+
+    /// Folder for the pages, inside the language's folder. Defaults to
+    /// `<docs-dir>/<lang>/api`.
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = openapi::PageSplit::Tag)]
+    by: openapi::PageSplit,
+
+    /// nav.md group the pages are listed under. Defaults to the spec's
+    /// `info.title`.
+    #[arg(long)]
+    group: Option<String>,
+
+    /// Regenerate the marked regions of pages that already exist, keeping
+    /// everything written around them.
+    #[arg(long, default_value_t = false)]
+    update: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 struct PageMeta {
     title: String,
     url: String,
     rel_slug: String,
     source_rel: String,
+    state: PageState,
+    /// The page's frontmatter, exposed to the template as `page.meta`.
+    meta: Frontmatter,
+    /// Set on a stand-in for a page this language has not translated: the
+    /// language whose file is rendered in its place (`fallback_pages: true`).
+    fallback_from: Option<String>,
+}
+
+/// Drafts are left out of `build` and shown with a banner by `serve`. Hidden
+/// pages are published and reachable by URL but never listed: not in the
+/// sidebar, the pager or search. Set with `draft:` / `hidden:` frontmatter,
+/// or by naming the file `name.draft.md` / `_name.md`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PageState {
+    #[default]
+    Published,
+    Draft,
+    Hidden,
+}
+
+/// A `---` YAML or `+++` TOML block at the top of a page. Known keys override
+/// what would otherwise be derived from the file (title from the first `# `
+/// line, slug from the filename, order from template.md); anything else is
+/// kept in `extra` and reaches the template untouched.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Frontmatter {
+    title: Option<String>,
+    description: Option<String>,
+    order: Option<i64>,
+    slug: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    hidden: bool,
+    layout: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -83,16 +288,40 @@ struct NavGroup {
     open: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 struct LangMeta {
     code: String,
     pages: Vec<PageMeta>,
 }
 
-#[derive(Clone, Debug)]
+/// Where the language switcher sends a reader of the current page.
+#[derive(Clone, Debug, Serialize)]
+struct Translation {
+    code: String,
+    url: String,
+    state: TranslationState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TranslationState {
+    /// The page being rendered.
+    Current,
+    /// The same page exists in this language.
+    Translated,
+    /// Only a stand-in in the default language exists (`fallback_pages`).
+    Fallback,
+    /// No such page; the link goes to the language root instead.
+    Missing,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 struct SiteMeta {
     langs: Vec<LangMeta>,
     default_lang: String,
+    /// Prefix for every URL the site emits: `""` at a domain root, otherwise
+    /// `/path` with no trailing slash.
+    base_path: String,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -103,6 +332,10 @@ struct SiteConfig {
     subtitle: Option<String>,
     theme: Option<String>,
     api_base: Option<String>,
+    code_samples: Option<Vec<String>>,
+    site_url: Option<String>,
+    robots: Option<String>,
+    fallback_pages: bool,
 }
 
 /// One entry in the "On this page" list, built from the `##`/`###` headings of
@@ -114,7 +347,8 @@ struct TocItem {
     id: String,
 }
 
-/// A single card in the JSON tab: a request, a response, or a curl sample.
+/// A single card in the JSON tab: a request, a response, one code sample, or
+/// the tabbed `samples` card holding every language's sample for a request.
 /// `code_html` is already highlighted and HTML-escaped.
 #[derive(Clone, Debug, Serialize)]
 struct ApiBlock {
@@ -128,12 +362,28 @@ struct ApiBlock {
     lang: String,
     code_html: String,
     generated: bool,
-    /// Unhighlighted source, kept so a curl sample can be derived from a
+    /// The tabs of a `samples` card, one block per language.
+    samples: Vec<ApiBlock>,
+    /// Unhighlighted source, kept so code samples can be derived from a
     /// request block. Not needed by the template.
     #[serde(skip)]
     raw: String,
 }
 
+/// A request and the cards that follow it up to the next request: its code
+/// samples and responses. Cards before the first request — an errors page
+/// has nothing else — form a group with no request and no anchor.
+#[derive(Clone, Debug, Serialize)]
+struct ApiEndpoint {
+    /// `endpoint-post-v1-resources`, unique within the page.
+    id: Option<String>,
+    method: Option<String>,
+    path: Option<String>,
+    /// The request heading's free text, when it has any.
+    label: Option<String>,
+    blocks: Vec<ApiBlock>,
+}
+
 /// Theme presets defined in `assets/input.css`. Adding a theme means adding a
 /// `[data-theme="name"]` block there (light + dark) and a name here.
 const THEMES: [&str; 6] = ["violet", "indigo", "ocean", "forest", "ember", "slate"];
@@ -152,6 +402,8 @@ struct SearchEntry {
     url: String,
     excerpt: String,
     content: String,
+    #[serde(skip)]
+    state: PageState,
 }
 
 #[actix_web::main]
@@ -160,183 +412,755 @@ async fn main() -> Result<()> {
 
     match cli.command {
         Commands::Build(args) => {
-            build_site(&args, false).context("build failed")?;
+            let files = SiteFiles::disk(&args.build.out_dir, args.build.force);
+            let out = files.staging();
+            let build = build_site(&args.build, &out, false).context("build failed")?;
+            if args.watch {
+                println!("Watching for changes; press Ctrl+C to stop.");
+                watch_and_rebuild(args.build, build, files, None)?;
+            }
         }
-        Commands::Serve(args) => {
-            let reload_state = Arc::new(AtomicU64::new(now_millis()));
-            let site = build_site(&args.build, args.watch).context("build failed")?;
-            reload_state.store(now_millis(), Ordering::Release);
+        Commands::Serve(mut args) => {
+            args.build.drafts = true;
+            let files = match args.write {
+                true => SiteFiles::disk(&args.build.out_dir, args.build.force),
+                false => SiteFiles::memory(),
+            };
+            let reload_state = reload::ReloadState::new();
+            let out = files.staging();
+            let build = build_site(&args.build, &out, args.watch).context("build failed")?;
+            files.publish(out);
+            reload_state.succeeded();
+            let site = build.site.clone();
             if args.watch {
-                start_watcher(args.build.clone(), reload_state.clone());
+                start_watcher(args.build.clone(), build, files.clone(), Some(reload_state.clone()));
             }
-            serve_site(args, site, reload_state).await?;
+            serve_site(args, site, files, reload_state).await?;
+        }
+        Commands::Check(args) => {
+            let diagnostics = check::check_site(&args.build).context("check failed")?;
+            check::report(&diagnostics, args.format)?;
+            let errors = diagnostics
+                .iter()
+                .filter(|d| d.severity == check::Severity::Error)
+                .count();
+            let warnings = diagnostics.len() - errors;
+            if errors > 0 || (args.strict && warnings > 0) {
+                return Err(anyhow!("check failed: {errors} error(s), {warnings} warning(s)"));
+            }
+        }
+        Commands::I18nStatus(args) => {
+            let entries = i18n::translation_status(&args.docs_dir)?;
+            i18n::report(&entries, args.format)?;
+        }
+        Commands::Export(ExportArgs { what: ExportCommand::Openapi(args) }) => {
+            let doc = openapi::export(
+                &args.docs_dir,
+                args.lang.as_deref(),
+                args.drafts,
+                &args.api_version,
+            )
+            .context("export failed")?;
+            openapi::write(&doc, args.format, args.out.as_deref())?;
+        }
+        Commands::Import(ImportArgs { what: ImportCommand::Openapi(args) }) => {
+            let report = openapi::import(&args).context("import failed")?;
+            for path in &report.written {
+                println!("wrote {}", path.display());
+            }
+            for path in &report.updated {
+                println!("updated {}", path.display());
+            }
+            println!(
+                "{} written, {} updated, {} unchanged; {} added to nav.md",
+                report.written.len(),
+                report.updated.len(),
+                report.unchanged,
+                report.nav_added
+            );
         }
     }
 
     Ok(())
 }
 
-fn build_site(args: &BuildArgs, dev_reload: bool) -> Result<SiteMeta> {
+/// What one rendered page contributed to the site. `serve --watch` keeps
+/// these so a change re-renders only the pages that read the changed file.
+struct RenderedPage {
+    url: String,
+    /// Every file the page was built from: its markdown, its includes, and
+    /// the nav.md / site.md files around it. Canonical paths, to compare
+    /// against the watcher's.
+    deps: HashSet<PathBuf>,
+    search: Option<SearchEntry>,
+    links: links::PageLinks,
+    /// Unresolved `.md` links, printed once the render phase is over.
+    diagnostics: Vec<check::Diagnostic>,
+}
+
+/// The result of a full build, and the starting point for incremental ones.
+struct SiteBuild {
+    site: SiteMeta,
+    tera: Tera,
+    /// Where each copied asset ended up; pages are rendered against it.
+    assets: AssetManifest,
+    /// In site order, which is also `search.json` order.
+    pages: Vec<RenderedPage>,
+}
+
+fn build_site(args: &BuildArgs, out: &Output, dev_reload: bool) -> Result<SiteBuild> {
     if !args.docs_dir.exists() {
         return Err(anyhow!("docs dir not found: {}", args.docs_dir.display()));
     }
 
-    let templates_glob = format!("{}/**/*.html", args.templates_dir.display());
-    let tera = Tera::new(&templates_glob)
-        .with_context(|| format!("failed to load templates: {templates_glob}"))?;
+    let tera = load_templates(args)?;
 
-    prepare_output_dir(&args.out_dir)?;
+    out.clear()?;
 
-    let site = collect_site_meta(&args.docs_dir)?;
-    let mut search_entries: Vec<SearchEntry> = Vec::new();
-    for lang in &site.langs {
-        for page in &lang.pages {
-            let nav_groups =
-                load_nav_groups(&args.docs_dir.join(&lang.code), &lang.pages, &page.url);
-            let site_config = load_site_config(&args.docs_dir, &lang.code);
-            let md_path = args
-                .docs_dir
-                .join(&lang.code)
-                .join(&page.source_rel);
-            if !md_path.exists() {
-                continue;
-            }
-            let markdown = fs::read_to_string(&md_path)
-                .with_context(|| format!("failed to read {}", md_path.display()))?;
-            let expanded = expand_includes(&markdown, md_path.parent().unwrap_or(&args.docs_dir))
-                .with_context(|| format!("failed to expand includes in {}", md_path.display()))?;
-            let (desc_md, arch_md, json_md, text_md) = split_sections(&expanded);
-            let content_html = markdown_to_html(&expanded);
-            let (description_html, toc) = markdown_to_html_with_toc(&desc_md);
-            let architecture_html = markdown_to_html(&arch_md);
-            let architecture_json_html = markdown_to_html(&json_md);
-            let architecture_text_html = markdown_to_html(&text_md);
-            let content_text = markdown_to_text(&expanded);
-            let excerpt = content_text.chars().take(160).collect::<String>();
-
-            let api_base = site_config
-                .api_base
-                .as_deref()
-                .unwrap_or(DEFAULT_API_BASE)
-                .trim_end_matches('/')
-                .to_string();
-            let api_blocks = build_api_blocks(&json_md, &api_base);
-            let (prev_page, next_page) = neighbour_pages(&nav_groups, &lang.pages, &page.url);
-            let breadcrumb = breadcrumb_for(&nav_groups, &page.url);
-
-            let mut ctx = TeraContext::new();
-            let title = site_config
-                .title
-                .as_deref()
-                .unwrap_or(&args.site_title);
-            ctx.insert("site_title", &title);
-            if let Some(logo) = &site_config.logo {
-                ctx.insert("site_logo", logo);
-            }
-            if let Some(footer) = &site_config.footer {
-                ctx.insert("site_footer", footer);
-            }
-            if let Some(subtitle) = &site_config.subtitle {
-                ctx.insert("site_subtitle", subtitle);
-            }
-            ctx.insert("site_theme", &resolve_theme(site_config.theme.as_deref()));
-            ctx.insert("page_title", &page.title);
-            ctx.insert("lang", &lang.code);
-            ctx.insert("content_html", &content_html);
-            ctx.insert("description_html", &description_html);
-            ctx.insert("architecture_html", &architecture_html);
-            ctx.insert("architecture_json_html", &architecture_json_html);
-            ctx.insert("architecture_text_html", &architecture_text_html);
-            ctx.insert("api_blocks", &api_blocks);
-            ctx.insert("toc", &toc);
-            ctx.insert("nav_groups", &nav_groups);
-            ctx.insert("nav_pages", &lang.pages);
-            ctx.insert("current_url", &page.url);
-            ctx.insert("langs", &site.langs);
-            ctx.insert("dev_reload", &dev_reload);
-            if let Some(prev) = &prev_page {
-                ctx.insert("prev_page", prev);
-            }
-            if let Some(next) = &next_page {
-                ctx.insert("next_page", next);
-            }
-            if let Some(crumb) = &breadcrumb {
-                ctx.insert("breadcrumb", crumb);
-            }
+    let base_path = resolve_base_path(args);
+    let site = collect_site_meta(&args.docs_dir, args.drafts, &base_path)?;
+
+    // `serve --watch` keeps plain names so stylesheets can be swapped in
+    // place; every other build fingerprints.
+    let assets = if dev_reload {
+        copy_assets(&args.assets_dir, out, "assets")?;
+        AssetManifest::plain(&base_path)
+    } else {
+        assets::copy_fingerprinted(&args.assets_dir, out, &base_path)?
+    };
+    let mut build = SiteBuild { site, tera, assets, pages: Vec::new() };
+    let all_pages: Vec<(&LangMeta, &PageMeta)> = build
+        .site
+        .langs
+        .iter()
+        .flat_map(|lang| lang.pages.iter().map(move |page| (lang, page)))
+        .collect();
+    let pages = render_pages(&build, args, out, &all_pages, dev_reload)?
+        .into_iter()
+        .flatten()
+        .collect();
+    build.pages = pages;
+    write_site_files(args, &build, out, dev_reload)?;
+    Ok(build)
+}
+
+/// What an incremental rebuild changed, which decides how open pages catch up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Rebuilt {
+    /// Pages or non-stylesheet assets: reload.
+    Site,
+    /// Only stylesheets under `assets_dir`: swap them in place.
+    Styles,
+}
+
+/// Bring a previous build up to date after `changed` files were touched,
+/// re-rendering only the pages that depend on them. Anything that changes
+/// page metadata — a title, a slug, a new or deleted page, nav ordering —
+/// can move every sidebar and link, so it falls back to a full build. Files
+/// under `assets_dir` are copied (or removed) one by one and render nothing,
+/// unless the build fingerprints them: then their names, and so every page,
+/// change, and it is a full build too.
+///
+/// Either way, once it succeeds, the output matches a fresh `build_site`.
+/// On disk, though, the re-rendered pages go straight into the live output
+/// rather than a staging directory, which would mean copying the whole site
+/// on every save. If a page fails, the ones rendered before it are already
+/// live beside the stale rest until `serve`, which drops `build` on an
+/// error, does a full build on the next change.
+fn rebuild_changed(
+    args: &BuildArgs,
+    build: &mut SiteBuild,
+    changed: &[PathBuf],
+    out: &Output,
+    dev_reload: bool,
+) -> Result<Rebuilt> {
+    let changed: HashSet<PathBuf> = changed.iter().map(|path| normalize_path(path)).collect();
+    let assets_dir = normalize_path(&args.assets_dir);
+    let (assets, changed): (HashSet<PathBuf>, HashSet<PathBuf>) =
+        changed.into_iter().partition(|path| path.starts_with(&assets_dir));
+    if build.assets.is_fingerprinted() && !assets.is_empty() {
+        *build = build_site(args, out, dev_reload)?;
+        return Ok(Rebuilt::Site);
+    }
+    for path in &assets {
+        sync_asset(&assets_dir, path, out)?;
+    }
+    if changed.is_empty() {
+        let styles_only = assets
+            .iter()
+            .all(|path| path.extension().is_some_and(|ext| ext == "css"));
+        return Ok(if styles_only { Rebuilt::Styles } else { Rebuilt::Site });
+    }
 
-            let rendered = tera
-                .render("page.html", &ctx)
-                .context("failed to render template")?;
+    let templates_dir = normalize_path(&args.templates_dir);
+    let templates_changed = changed.iter().any(|path| path.starts_with(&templates_dir));
+    if templates_changed {
+        build.tera = load_templates(args)?;
+    }
 
-            let out_path = output_path_for(&args.out_dir, &lang.code, &page.rel_slug);
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)
-                    .with_context(|| format!("failed to create {}", parent.display()))?;
-            }
-            fs::write(&out_path, rendered)
-                .with_context(|| format!("failed to write {}", out_path.display()))?;
+    let site = collect_site_meta(&args.docs_dir, args.drafts, &resolve_base_path(args))?;
+    if site != build.site {
+        *build = build_site(args, out, dev_reload)?;
+        return Ok(Rebuilt::Site);
+    }
 
-            search_entries.push(SearchEntry {
-                lang: lang.code.clone(),
-                title: page.title.clone(),
-                url: page.url.clone(),
-                excerpt,
-                content: content_text,
-            });
+    let mut stale = Vec::new();
+    let mut todo = Vec::new();
+    for (idx, rendered) in build.pages.iter().enumerate() {
+        if !templates_changed && rendered.deps.is_disjoint(&changed) {
+            continue;
+        }
+        let found = build.site.langs.iter().find_map(|lang| {
+            lang.pages
+                .iter()
+                .find(|page| page.url == rendered.url)
+                .map(|page| (lang, page))
+        });
+        if let Some(found) = found {
+            stale.push(idx);
+            todo.push(found);
+        }
+    }
+    let fresh = render_pages(build, args, out, &todo, dev_reload)?;
+    for (idx, page) in stale.into_iter().zip(fresh) {
+        if let Some(page) = page {
+            build.pages[idx] = page;
         }
     }
 
-    copy_assets(&args.assets_dir, &args.out_dir.join("assets"))?;
-    write_search_index(&args.out_dir, &search_entries)?;
-    write_root_index(&args.out_dir, &site.default_lang)?;
+    write_site_files(args, build, out, dev_reload)?;
+    Ok(Rebuilt::Site)
+}
 
-    let marker = args.out_dir.join(".docsgen");
-    fs::write(marker, "managed by docsgen")?;
+fn load_templates(args: &BuildArgs) -> Result<Tera> {
+    let templates_glob = format!("{}/**/*.html", args.templates_dir.display());
+    Tera::new(&templates_glob).with_context(|| format!("failed to load templates: {templates_glob}"))
+}
 
-    Ok(site)
+/// What every page of one language reads besides its own markdown: the
+/// merged site.md settings and the nav.md sidebar. Loaded once per language
+/// before any page renders.
+struct LangInputs {
+    site_config: SiteConfig,
+    /// Every group closed; `open_nav_group` opens the current page's.
+    nav_groups: Vec<NavGroup>,
+}
+
+fn load_lang_inputs(args: &BuildArgs, site: &SiteMeta) -> HashMap<String, LangInputs> {
+    site.langs
+        .iter()
+        .map(|lang| {
+            let inputs = LangInputs {
+                site_config: load_site_config(&args.docs_dir, &lang.code),
+                nav_groups: load_nav_groups(&args.docs_dir.join(&lang.code), &lang.pages),
+            };
+            (lang.code.clone(), inputs)
+        })
+        .collect()
+}
+
+/// Render `pages` on a pool of worker threads (`--jobs`, one per CPU by
+/// default). Results and link warnings come back in the order of `pages`,
+/// whichever worker finished first, so `search.json` and the build log are
+/// the same on every run. The first failing page, in that order, is the
+/// error returned.
+fn render_pages(
+    build: &SiteBuild,
+    args: &BuildArgs,
+    out: &Output,
+    pages: &[(&LangMeta, &PageMeta)],
+    dev_reload: bool,
+) -> Result<Vec<Option<RenderedPage>>> {
+    let inputs = load_lang_inputs(args, &build.site);
+    let source_urls = links::SourceUrls::new(&build.site);
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZero::get))
+        .clamp(1, pages.len().max(1));
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let mut results: Vec<Option<Result<Option<RenderedPage>>>> =
+        (0..pages.len()).map(|_| None).collect();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    // Pages are claimed in order, so once one fails every
+                    // page before it has been claimed and will finish.
+                    while !failed.load(Ordering::Relaxed) {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((lang, page)) = pages.get(idx) else {
+                            break;
+                        };
+                        let lang_inputs = &inputs[&lang.code];
+                        let result = render_page(
+                            build,
+                            args,
+                            out,
+                            &source_urls,
+                            lang_inputs,
+                            lang,
+                            page,
+                            dev_reload,
+                        );
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        done.push((idx, result));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (idx, result) in worker.join().expect("render worker panicked") {
+                results[idx] = Some(result);
+            }
+        }
+    });
+
+    let mut rendered = Vec::with_capacity(pages.len());
+    for result in results {
+        let Some(result) = result else {
+            break;
+        };
+        let page = result?;
+        if let Some(page) = &page {
+            for diagnostic in &page.diagnostics {
+                eprintln!("{diagnostic}");
+            }
+        }
+        rendered.push(page);
+    }
+    Ok(rendered)
 }
 
-fn write_root_index(out_dir: &Path, default_lang: &str) -> Result<()> {
-    let target = format!("/{default_lang}/");
+/// Render one page to `out`. `None` when its markdown file is gone.
+#[allow(clippy::too_many_arguments)]
+fn render_page(
+    build: &SiteBuild,
+    args: &BuildArgs,
+    out: &Output,
+    source_urls: &links::SourceUrls,
+    inputs: &LangInputs,
+    lang: &LangMeta,
+    page: &PageMeta,
+    dev_reload: bool,
+) -> Result<Option<RenderedPage>> {
+    let site = &build.site;
+    let listed_pages: Vec<&PageMeta> = lang
+        .pages
+        .iter()
+        .filter(|page| page.state != PageState::Hidden)
+        .collect();
+    let nav_groups = open_nav_group(&inputs.nav_groups, &page.url);
+    let site_config = &inputs.site_config;
+    let source_lang = page.fallback_from.as_deref().unwrap_or(&lang.code);
+    let md_path = args
+        .docs_dir
+        .join(source_lang)
+        .join(&page.source_rel);
+    if !md_path.exists() {
+        return Ok(None);
+    }
+    let mut deps = vec![
+        md_path.clone(),
+        args.docs_dir.join("site.md"),
+        args.docs_dir.join(&lang.code).join("site.md"),
+        args.docs_dir.join(&lang.code).join("nav.md"),
+    ];
+    let source = fs::read_to_string(&md_path)
+        .with_context(|| format!("failed to read {}", md_path.display()))?;
+    let (_, markdown) = split_frontmatter(&source)
+        .with_context(|| format!("invalid frontmatter in {}", md_path.display()))?;
+    let base_dir = md_path.parent().unwrap_or(&args.docs_dir);
+    let expanded = expand_includes_tracked(markdown, base_dir, 0, &mut deps)
+        .with_context(|| format!("failed to expand includes in {}", md_path.display()))?;
+    let (desc_md, arch_md, json_md, text_md) = split_sections(&expanded);
+    let md_links = links::MdLinks::new(source_urls, &lang.code, &page.source_rel);
+    let content_html = markdown_to_html(&expanded, Some(&md_links));
+    let (description_html, toc) = markdown_to_html_with_toc(&desc_md, Some(&md_links));
+    let architecture_html = markdown_to_html(&arch_md, Some(&md_links));
+    let architecture_json_html = markdown_to_html(&json_md, Some(&md_links));
+    let architecture_text_html = markdown_to_html(&text_md, Some(&md_links));
+    let content_text = markdown_to_text(&expanded);
+    let excerpt = match &page.meta.description {
+        Some(description) => description.clone(),
+        None => content_text.chars().take(160).collect::<String>(),
+    };
+
+    let api_base = site_config
+        .api_base
+        .as_deref()
+        .unwrap_or(DEFAULT_API_BASE)
+        .trim_end_matches('/')
+        .to_string();
+    let languages = samples::languages(site_config.code_samples.as_deref());
+    let api_endpoints = build_api_endpoints(&json_md, &api_base, &languages);
+    let api_blocks: Vec<&ApiBlock> = api_endpoints
+        .iter()
+        .flat_map(|endpoint| &endpoint.blocks)
+        .collect();
+    let (prev_page, next_page) = neighbour_pages(&nav_groups, &lang.pages, &page.url);
+    let breadcrumb = breadcrumb_for(&nav_groups, &page.url);
+
+    let mut ctx = site_context(args, build, site_config, dev_reload);
+    ctx.insert("page_title", &page.title);
+    ctx.insert("page", page);
+    ctx.insert("lang", &lang.code);
+    ctx.insert("content_html", &content_html);
+    ctx.insert("description_html", &description_html);
+    ctx.insert("architecture_html", &architecture_html);
+    ctx.insert("architecture_json_html", &architecture_json_html);
+    ctx.insert("architecture_text_html", &architecture_text_html);
+    ctx.insert("api_blocks", &api_blocks);
+    ctx.insert("api_endpoints", &api_endpoints);
+    ctx.insert("toc", &toc);
+    ctx.insert("nav_groups", &nav_groups);
+    ctx.insert("nav_pages", &listed_pages);
+    ctx.insert("current_url", &page.url);
+    ctx.insert("translations", &translations_for(site, &lang.code, &page.rel_slug));
+    ctx.insert("untranslated", &page.fallback_from.is_some());
+    ctx.insert("noindex", &page.fallback_from.is_some());
+    if let Some(site_url) = &site_config.site_url {
+        let origin = seo::site_origin(site_url);
+        // A stand-in points search engines at the page it copies.
+        let canonical = url_for(&site.base_path, source_lang, &page.rel_slug);
+        ctx.insert("canonical_url", &format!("{origin}{canonical}"));
+        ctx.insert("alternate_links", &seo::alternates(site, &origin, &page.rel_slug));
+    }
+    if let Some(prev) = &prev_page {
+        ctx.insert("prev_page", prev);
+    }
+    if let Some(next) = &next_page {
+        ctx.insert("next_page", next);
+    }
+    if let Some(crumb) = &breadcrumb {
+        ctx.insert("breadcrumb", crumb);
+    }
+
+    let layout = match &page.meta.layout {
+        Some(layout) => format!("{layout}.html"),
+        None => "page.html".to_string(),
+    };
+    let rendered = build
+        .tera
+        .render(&layout, &ctx)
+        .with_context(|| format!("failed to render template {layout}"))?;
+
+    out.write(
+        &output_path_for(&lang.code, &page.rel_slug),
+        build.assets.rewrite(&rendered),
+    )?;
+
+    // A stand-in is the default language's text; searching in this
+    // language should not turn it up.
+    let search = page.fallback_from.is_none().then(|| SearchEntry {
+        lang: lang.code.clone(),
+        title: page.title.clone(),
+        url: page.url.clone(),
+        excerpt,
+        content: content_text,
+        state: page.state,
+    });
+    // content_html renders the whole page again, so each unresolved link
+    // was recorded twice; `diagnostics` reports it once.
+    let diagnostics = match page.fallback_from {
+        None => md_links.diagnostics(&md_path, &source),
+        Some(_) => Vec::new(),
+    };
+    let anchors: Vec<String> =
+        api_endpoints.iter().filter_map(|endpoint| endpoint.id.clone()).collect();
+    let links = links::PageLinks::new(
+        &page.url,
+        &md_path,
+        &source,
+        &[
+            &description_html,
+            &architecture_html,
+            &architecture_json_html,
+            &architecture_text_html,
+        ],
+        &anchors,
+    );
+
+    Ok(Some(RenderedPage {
+        url: page.url.clone(),
+        deps: deps.iter().map(|path| normalize_path(path)).collect(),
+        search,
+        links,
+        diagnostics,
+    }))
+}
+
+/// Everything outside the pages themselves. Cheap enough to redo on every
+/// incremental rebuild: the 404 pages, link check, asset manifest, search
+/// index, root redirect, sitemap and robots.txt.
+fn write_site_files(
+    args: &BuildArgs,
+    build: &SiteBuild,
+    out: &Output,
+    dev_reload: bool,
+) -> Result<()> {
+    let site = &build.site;
+    let inputs = load_lang_inputs(args, site);
+    for lang in &site.langs {
+        let listed_pages: Vec<&PageMeta> = lang
+            .pages
+            .iter()
+            .filter(|page| page.state != PageState::Hidden)
+            .collect();
+        let lang_inputs = &inputs[&lang.code];
+        write_not_found_page(
+            build,
+            args,
+            out,
+            lang_inputs,
+            lang,
+            &listed_pages,
+            dev_reload,
+        )?;
+    }
+
+    let page_links = build.pages.iter().map(|page| &page.links);
+    let broken_links = links::check_links(page_links, &site.base_path);
+    for diagnostic in &broken_links {
+        eprintln!("{diagnostic}");
+    }
+    if args.deny_broken_links && !broken_links.is_empty() {
+        return Err(anyhow!("{} broken link(s)", broken_links.len()));
+    }
+
+    build.assets.write(out)?;
+
+    let search_entries: Vec<&SearchEntry> =
+        build.pages.iter().filter_map(|page| page.search.as_ref()).collect();
+    write_search_index(out, &search_entries)?;
+    write_root_index(out, site)?;
+    // Static hosts (Netlify, GitHub Pages, S3 error documents) look for a
+    // 404.html at the root; give them the default language's.
+    let not_found_page = out.read(&format!("{}/404.html", site.default_lang))?;
+    out.write("404.html", not_found_page)?;
+
+    let site_config = &inputs[&site.default_lang].site_config;
+    let origin = site_config.site_url.as_deref().map(seo::site_origin);
+    if let Some(origin) = &origin {
+        seo::write_sitemap(out, site, origin)?;
+    }
+    seo::write_robots(
+        out,
+        &args.docs_dir,
+        site,
+        site_config.robots.as_deref(),
+        origin.as_deref(),
+    )?;
+
+    out.finish()
+}
+
+/// Template values every page shares: the site-wide settings for one language.
+fn site_context(
+    args: &BuildArgs,
+    build: &SiteBuild,
+    site_config: &SiteConfig,
+    dev_reload: bool,
+) -> TeraContext {
+    let site = &build.site;
+    let mut ctx = TeraContext::new();
+    let title = site_config
+        .title
+        .as_deref()
+        .unwrap_or(&args.site_title);
+    ctx.insert("site_title", &title);
+    if let Some(logo) = &site_config.logo {
+        let logo = build.assets.url(&with_base_path(&site.base_path, logo));
+        ctx.insert("site_logo", &logo);
+    }
+    if let Some(footer) = &site_config.footer {
+        ctx.insert("site_footer", footer);
+    }
+    if let Some(subtitle) = &site_config.subtitle {
+        ctx.insert("site_subtitle", subtitle);
+    }
+    ctx.insert("site_theme", &resolve_theme(site_config.theme.as_deref()));
+    ctx.insert("langs", &site.langs);
+    ctx.insert("dev_reload", &dev_reload);
+    ctx.insert("base_path", &site.base_path);
+    ctx
+}
+
+/// `{lang}/404.html`: the regular page chrome — sidebar, search, language
+/// pills — around a short "not found" message, so a reader who followed a
+/// dead link can find their way from there.
+fn write_not_found_page(
+    build: &SiteBuild,
+    args: &BuildArgs,
+    out: &Output,
+    inputs: &LangInputs,
+    lang: &LangMeta,
+    listed_pages: &[&PageMeta],
+    dev_reload: bool,
+) -> Result<()> {
+    let site = &build.site;
+    let home = url_for(&site.base_path, &lang.code, "index");
+    let page = PageMeta {
+        title: "Page not found".to_string(),
+        url: format!("{}/{}/404.html", site.base_path, lang.code),
+        rel_slug: "404".to_string(),
+        ..PageMeta::default()
+    };
+    let description_html = format!(
+        "<p>There is no page at this address. It may have moved, or the link that brought you here has a typo.</p>\n\
+<p><a href=\"{}\">Go to the home page</a> or search the docs above.</p>\n",
+        escape_html(&home)
+    );
+    let translations: Vec<Translation> = site
+        .langs
+        .iter()
+        .map(|other| Translation {
+            code: other.code.clone(),
+            url: url_for(&site.base_path, &other.code, "index"),
+            state: if other.code == lang.code {
+                TranslationState::Current
+            } else {
+                TranslationState::Translated
+            },
+        })
+        .collect();
+
+    let mut ctx = site_context(args, build, &inputs.site_config, dev_reload);
+    ctx.insert("page_title", &page.title);
+    ctx.insert("page", &page);
+    ctx.insert("lang", &lang.code);
+    ctx.insert("content_html", &description_html);
+    ctx.insert("description_html", &description_html);
+    ctx.insert("architecture_html", "");
+    ctx.insert("architecture_json_html", "");
+    ctx.insert("architecture_text_html", "");
+    ctx.insert("api_blocks", &Vec::<ApiBlock>::new());
+    ctx.insert("api_endpoints", &Vec::<ApiEndpoint>::new());
+    ctx.insert("toc", &Vec::<TocItem>::new());
+    ctx.insert("nav_groups", &inputs.nav_groups);
+    ctx.insert("nav_pages", listed_pages);
+    ctx.insert("current_url", &page.url);
+    ctx.insert("translations", &translations);
+    ctx.insert("untranslated", &false);
+    ctx.insert("noindex", &true);
+
+    let rendered = build
+        .tera
+        .render("page.html", &ctx)
+        .context("failed to render template page.html")?;
+    out.write(&format!("{}/404.html", lang.code), build.assets.rewrite(&rendered))
+}
+
+/// The root page picks the reader's language from `navigator.languages` the
+/// same way `serve` reads `Accept-Language` (see `negotiate_lang`), and falls
+/// back to the default language without JavaScript.
+fn write_root_index(out: &Output, site: &SiteMeta) -> Result<()> {
+    let target = format!("{}/{}/", site.base_path, site.default_lang);
+    let codes: Vec<&str> = site.langs.iter().map(|lang| lang.code.as_str()).collect();
+    let codes = serde_json::to_string(&codes)?;
+    let default_lang = serde_json::to_string(&site.default_lang)?;
+    let base_path = serde_json::to_string(&site.base_path)?;
     let html = format!(
         "<!doctype html>\n\
-<html lang=\"en\">\n\
+<html lang=\"{lang}\">\n\
 <head>\n\
   <meta charset=\"utf-8\">\n\
   <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
   <title>Redirecting...</title>\n\
-  <meta http-equiv=\"refresh\" content=\"0; url={target}\">\n\
-  <script>window.location.replace('{target}');</script>\n\
+  <noscript><meta http-equiv=\"refresh\" content=\"0; url={target}\"></noscript>\n\
+  <script>\n\
+    const langs = {codes};\n\
+    const wanted = navigator.languages || [navigator.language || \"\"];\n\
+    let pick = {default_lang};\n\
+    for (const tag of wanted.map((t) => t.toLowerCase())) {{\n\
+      const hit = langs.find((code) => code.toLowerCase() === tag)\n\
+        || langs.find((code) => code.toLowerCase() === tag.split(\"-\")[0])\n\
+        || langs.find((code) => code.toLowerCase().split(\"-\")[0] === tag.split(\"-\")[0]);\n\
+      if (hit) {{ pick = hit; break; }}\n\
+    }}\n\
+    window.location.replace({base_path} + \"/\" + pick + \"/\");\n\
+  </script>\n\
 </head>\n\
 <body>\n\
   <p>Redirecting to <a href=\"{target}\">{target}</a></p>\n\
 </body>\n\
-</html>\n"
+</html>\n",
+        lang = site.default_lang,
     );
-    fs::write(out_dir.join("index.html"), html)
-        .with_context(|| format!("failed to write {}/index.html", out_dir.display()))?;
-    Ok(())
+    out.write("index.html", html)
 }
 
+/// The site is mounted under `base_path`, so a dev session previews exactly
+/// the URLs the deployed site will have.
 async fn serve_site(
     args: ServeArgs,
     site: SiteMeta,
-    reload_state: Arc<AtomicU64>,
+    files: SiteFiles,
+    reload_state: Arc<reload::ReloadState>,
 ) -> Result<()> {
-    let out_dir = args.build.out_dir.clone();
-    let default_lang = site.default_lang.clone();
+    let base_path = site.base_path.clone();
+    let site = web::Data::new(site);
 
     let bind_addr = format!("{}:{}", args.host, args.port);
-    println!("Serving on http://{bind_addr}");
+    println!("Serving on http://{bind_addr}{base_path}/");
+
+    let lang_pattern = lang_route_pattern(&site);
 
     HttpServer::new(move || {
+        let base_path = base_path.clone();
+        let scope = web::scope(&base_path)
+            .route("", web::get().to(root_redirect))
+            .route("/__reload", web::get().to(reload::reload_poll))
+            .route("/__events", web::get().to(reload::reload_events))
+            .route("/", web::get().to(root_redirect))
+            .route(&lang_pattern, web::get().to(lang_redirect));
+        let scope = match &files {
+            SiteFiles::Disk { out_dir, .. } => {
+                let not_found_site = site.clone();
+                let not_found_files = files.clone();
+                scope.service(
+                    Files::new("/", out_dir)
+                        .index_file("index.html")
+                        .default_handler(fn_service(move |req: ServiceRequest| {
+                            let res =
+                                not_found_response(req.path(), &not_found_site, &not_found_files);
+                            async move { Ok(req.into_response(res)) }
+                        })),
+                )
+            }
+            SiteFiles::Memory(_) => scope.default_service(web::to(output::serve_file)),
+        };
+        let cache_files = files.clone();
+        let cache_base = base_path.clone();
         App::new()
-            .app_data(web::Data::new(default_lang.clone()))
+            // The dev server answers with the `_headers` a deploy would.
+            .wrap_fn(move |req, srv| {
+                let immutable = assets::is_immutable(&cache_files, &cache_base, req.path());
+                let res = srv.call(req);
+                async move {
+                    let mut res = res.await?;
+                    if immutable && res.status().is_success() {
+                        res.headers_mut()
+                            .insert(CACHE_CONTROL, HeaderValue::from_static(assets::IMMUTABLE));
+                    }
+                    Ok(res)
+                }
+            })
+            .app_data(site.clone())
             .app_data(web::Data::new(reload_state.clone()))
-            .route("/__reload", web::get().to(reload_poll))
-            .route("/", web::get().to(root_redirect))
-            .route("/{lang}", web::get().to(lang_redirect))
-            .service(Files::new("/", &out_dir).index_file("index.html"))
+            .app_data(web::Data::new(files.clone()))
+            .configure(|cfg| {
+                if !base_path.is_empty() {
+                    cfg.route("/", web::get().to(root_redirect));
+                }
+            })
+            .service(scope)
+            .default_service(web::to(not_found))
     })
     .bind(bind_addr)?
     .run()
@@ -345,38 +1169,107 @@ async fn serve_site(
     Ok(())
 }
 
-async fn root_redirect(default_lang: web::Data<String>) -> impl Responder {
+async fn root_redirect(req: HttpRequest, site: web::Data<SiteMeta>) -> impl Responder {
+    let accept = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let codes: Vec<&str> = site.langs.iter().map(|lang| lang.code.as_str()).collect();
+    let lang = negotiate_lang(accept, &codes).unwrap_or(&site.default_lang);
     HttpResponse::Found()
-        .append_header((LOCATION, format!("/{}/", default_lang.get_ref())))
+        .append_header((LOCATION, format!("{}/{}/", site.base_path, lang)))
+        .append_header((VARY, "Accept-Language"))
         .finish()
 }
 
-async fn lang_redirect(path: web::Path<String>) -> impl Responder {
+/// The best of `available` for an `Accept-Language` header, by q-value and
+/// then header order. `hi-IN` matches `hi`, and `pt` matches `pt-BR`. `None`
+/// means nothing matched and the caller falls back to the default language.
+fn negotiate_lang<'a>(header: &str, available: &[&'a str]) -> Option<&'a str> {
+    let mut wanted: Vec<(f32, &str)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && q > 0.0).then_some((q, tag))
+        })
+        .collect();
+    // A stable sort keeps header order among equal q-values.
+    wanted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let primary = |tag: &str| tag.split('-').next().unwrap_or_default().to_ascii_lowercase();
+    wanted.iter().find_map(|(_, tag)| {
+        available
+            .iter()
+            .find(|code| code.eq_ignore_ascii_case(tag))
+            .or_else(|| available.iter().find(|code| code.eq_ignore_ascii_case(&primary(tag))))
+            .or_else(|| available.iter().find(|code| primary(code) == primary(tag)))
+            .copied()
+    })
+}
+
+async fn lang_redirect(site: web::Data<SiteMeta>, path: web::Path<String>) -> impl Responder {
     let lang = path.into_inner();
     HttpResponse::Found()
-        .append_header((LOCATION, format!("/{}/", lang)))
+        .append_header((LOCATION, format!("{}/{}/", site.base_path, lang)))
         .finish()
 }
 
-async fn reload_poll(state: web::Data<Arc<AtomicU64>>) -> impl Responder {
-    let value = state.load(Ordering::Acquire);
-    HttpResponse::Ok()
-        .content_type("text/plain")
-        .body(value.to_string())
+/// Paths outside `base_path` never reach the file service.
+async fn not_found(
+    req: HttpRequest,
+    site: web::Data<SiteMeta>,
+    files: web::Data<SiteFiles>,
+) -> HttpResponse {
+    not_found_response(req.path(), &site, &files)
 }
 
-fn prepare_output_dir(out_dir: &Path) -> Result<()> {
-    let marker = out_dir.join(".docsgen");
-    if out_dir.exists() && marker.exists() {
-        fs::remove_dir_all(out_dir)
-            .with_context(|| format!("failed to clean {}", out_dir.display()))?;
-    }
-    fs::create_dir_all(out_dir)
-        .with_context(|| format!("failed to create {}", out_dir.display()))?;
-    Ok(())
+/// The generated `404.html` of the language the path is under, or of the
+/// default language, with a real 404 status.
+fn not_found_response(path: &str, site: &SiteMeta, files: &SiteFiles) -> HttpResponse {
+    let rest = path.strip_prefix(site.base_path.as_str()).unwrap_or(path);
+    let first = rest.trim_start_matches('/').split('/').next().unwrap_or_default();
+    let lang = site
+        .langs
+        .iter()
+        .find(|lang| lang.code == first)
+        .map_or(site.default_lang.as_str(), |lang| lang.code.as_str());
+    match files.read(&format!("{lang}/404.html")) {
+        Some(html) => HttpResponse::NotFound()
+            .content_type("text/html; charset=utf-8")
+            .body(html),
+        None => HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body("404 Not Found"),
+    }
+}
+
+/// `/{lang}` only matches real language folders; any other single segment
+/// (`/search.json`, `/typo`) falls through to the files. Codes are folder
+/// names, so anything the pattern would read as regex syntax is escaped.
+fn lang_route_pattern(site: &SiteMeta) -> String {
+    let codes: Vec<String> = site
+        .langs
+        .iter()
+        .map(|lang| {
+            lang.code
+                .chars()
+                .flat_map(|c| {
+                    let escape = !c.is_ascii_alphanumeric() && c != '-' && c != '_';
+                    escape.then_some('\\').into_iter().chain(std::iter::once(c))
+                })
+                .collect()
+        })
+        .collect();
+    format!("/{{lang:{}}}", codes.join("|"))
 }
 
-fn collect_site_meta(docs_dir: &Path) -> Result<SiteMeta> {
+fn collect_site_meta(docs_dir: &Path, include_drafts: bool, base_path: &str) -> Result<SiteMeta> {
     let mut langs = Vec::new();
     for entry in fs::read_dir(docs_dir)? {
         let entry = entry?;
@@ -384,7 +1277,7 @@ fn collect_site_meta(docs_dir: &Path) -> Result<SiteMeta> {
             continue;
         }
         let lang_code = entry.file_name().to_string_lossy().to_string();
-        let pages = collect_pages_for_lang(&entry.path(), &lang_code)?;
+        let pages = collect_pages_for_lang(&entry.path(), &lang_code, include_drafts, base_path)?;
         if !pages.is_empty() {
             langs.push(LangMeta { code: lang_code, pages });
         }
@@ -395,12 +1288,41 @@ fn collect_site_meta(docs_dir: &Path) -> Result<SiteMeta> {
     }
 
     langs.sort_by(|a, b| a.code.cmp(&b.code));
-    let default_lang = langs.first().unwrap().code.clone();
+    let default_lang = match top_level_site_value(docs_dir, "default_lang") {
+        Some(code) if langs.iter().any(|lang| lang.code == code) => code,
+        Some(code) => {
+            return Err(anyhow!(
+                "default_lang '{code}' in site.md has no pages under {}",
+                docs_dir.join(&code).display()
+            ));
+        }
+        None => langs.first().unwrap().code.clone(),
+    };
+
+    let default_pages = langs
+        .iter()
+        .find(|lang| lang.code == default_lang)
+        .map(|lang| lang.pages.clone())
+        .unwrap_or_default();
+    for lang in langs.iter_mut().filter(|lang| lang.code != default_lang) {
+        if load_site_config(docs_dir, &lang.code).fallback_pages {
+            add_fallback_pages(lang, &default_pages, &default_lang, base_path);
+        }
+    }
 
-    Ok(SiteMeta { langs, default_lang })
+    Ok(SiteMeta {
+        langs,
+        default_lang,
+        base_path: base_path.to_string(),
+    })
 }
 
-fn collect_pages_for_lang(lang_dir: &Path, lang_code: &str) -> Result<Vec<PageMeta>> {
+fn collect_pages_for_lang(
+    lang_dir: &Path,
+    lang_code: &str,
+    include_drafts: bool,
+    base_path: &str,
+) -> Result<Vec<PageMeta>> {
     let mut pages = Vec::new();
     let include_order = load_include_order(lang_dir).unwrap_or_default();
     for entry in WalkDir::new(lang_dir)
@@ -416,21 +1338,49 @@ fn collect_pages_for_lang(lang_dir: &Path, lang_code: &str) -> Result<Vec<PageMe
         }
         let rel_path = entry.path().strip_prefix(lang_dir)?;
         let source_rel = rel_path.to_string_lossy().replace('\\', "/");
-        let mut rel_slug = path_without_extension(rel_path);
+        // Config files, not content: they drive the sidebar and site settings
+        // and must not be published as pages of their own, nor parsed as one.
+        if matches!(source_rel.as_str(), "template.md" | "nav.md" | "site.md") {
+            continue;
+        }
+        let (mut rel_slug, named_state) = state_from_file_name(&path_without_extension(rel_path));
         if rel_slug == "welcome" {
             rel_slug = "index".to_string();
         }
-        let markdown = fs::read_to_string(entry.path())
+        let source = fs::read_to_string(entry.path())
             .with_context(|| format!("failed to read {}", entry.path().display()))?;
-        let expanded = expand_includes(&markdown, lang_dir)
-            .with_context(|| format!("failed to expand includes in {}", entry.path().display()))?;
-        let title = extract_title(&expanded)
+        let (meta, markdown) = split_frontmatter(&source)
+            .with_context(|| format!("invalid frontmatter in {}", entry.path().display()))?;
+        // Includes resolve against the page's own directory, as they do when
+        // it renders. A broken one fails that render too; the title can
+        // still come from the page's own text.
+        let page_dir = entry.path().parent().unwrap_or(lang_dir);
+        let expanded = match expand_includes(markdown, page_dir) {
+            Ok(expanded) => expanded,
+            Err(err) => {
+                let message = format!("{err:#}; taking the title from the page's own text");
+                let diagnostic = check::Diagnostic::warning("include", entry.path(), None, message);
+                eprintln!("{diagnostic}");
+                markdown.to_string()
+            }
+        };
+        if let Some(slug) = meta.slug.as_deref().map(|s| s.trim_matches('/')).filter(|s| !s.is_empty()) {
+            rel_slug = slug.to_string();
+        }
+        let title = meta
+            .title
+            .clone()
+            .or_else(|| extract_title(&expanded))
             .unwrap_or_else(|| title_from_slug(&rel_slug));
-        let url = url_for(lang_code, &rel_slug);
-
-        // Config files, not content: they drive the sidebar and site settings
-        // and must not be published as pages of their own.
-        if matches!(source_rel.as_str(), "template.md" | "nav.md" | "site.md") {
+        let url = url_for(base_path, lang_code, &rel_slug);
+        let state = if meta.draft || named_state == PageState::Draft {
+            PageState::Draft
+        } else if meta.hidden || named_state == PageState::Hidden {
+            PageState::Hidden
+        } else {
+            PageState::Published
+        };
+        if state == PageState::Draft && !include_drafts {
             continue;
         }
 
@@ -439,38 +1389,149 @@ fn collect_pages_for_lang(lang_dir: &Path, lang_code: &str) -> Result<Vec<PageMe
             url,
             rel_slug,
             source_rel,
+            state,
+            meta,
+            fallback_from: None,
         });
     }
 
+    // The home page always leads; an explicit `order:` comes next, then the
+    // template.md / filename order for everything else.
     pages.sort_by(|a, b| {
         let a_idx = order_index(&include_order, &a.source_rel, &a.rel_slug);
         let b_idx = order_index(&include_order, &b.source_rel, &b.rel_slug);
-        a_idx
-            .cmp(&b_idx)
+        (a.rel_slug != "index")
+            .cmp(&(b.rel_slug != "index"))
+            .then_with(|| {
+                let a_order = a.meta.order.unwrap_or(i64::MAX);
+                let b_order = b.meta.order.unwrap_or(i64::MAX);
+                a_order.cmp(&b_order)
+            })
+            .then_with(|| a_idx.cmp(&b_idx))
             .then_with(|| a.rel_slug.cmp(&b.rel_slug))
     });
     Ok(pages)
 }
 
+/// Fill the gaps in `lang` with the default language's pages, published at
+/// this language's URLs. Translated pages keep their place; the stand-ins
+/// follow in the default language's order, with a missing home page still
+/// leading.
+fn add_fallback_pages(
+    lang: &mut LangMeta,
+    default_pages: &[PageMeta],
+    default_lang: &str,
+    base_path: &str,
+) {
+    for page in default_pages {
+        if lang.pages.iter().any(|own| own.rel_slug == page.rel_slug) {
+            continue;
+        }
+        lang.pages.push(PageMeta {
+            url: url_for(base_path, &lang.code, &page.rel_slug),
+            fallback_from: Some(default_lang.to_string()),
+            ..page.clone()
+        });
+    }
+    lang.pages.sort_by_key(|page| page.rel_slug != "index");
+}
+
 fn path_without_extension(path: &Path) -> String {
     let mut rel = path.to_path_buf();
     rel.set_extension("");
     rel.to_string_lossy().replace('\\', "/")
 }
 
-fn url_for(lang: &str, rel_slug: &str) -> String {
+/// `guides/setup.draft` → `guides/setup` as a draft, `_setup` → `setup` as a
+/// hidden page. The marker is dropped from the slug so publishing the page
+/// later does not change its URL.
+fn state_from_file_name(rel_slug: &str) -> (String, PageState) {
+    let (dir, name) = match rel_slug.rsplit_once('/') {
+        Some((dir, name)) => (format!("{dir}/"), name),
+        None => (String::new(), rel_slug),
+    };
+    if let Some(stem) = name.strip_suffix(".draft").filter(|s| !s.is_empty()) {
+        return (format!("{dir}{stem}"), PageState::Draft);
+    }
+    if let Some(stem) = name.strip_prefix('_').filter(|s| !s.is_empty()) {
+        return (format!("{dir}{stem}"), PageState::Hidden);
+    }
+    (rel_slug.to_string(), PageState::Published)
+}
+
+fn url_for(base_path: &str, lang: &str, rel_slug: &str) -> String {
     if rel_slug == "index" {
-        format!("/{lang}/")
+        format!("{base_path}/{lang}/")
     } else {
-        format!("/{lang}/{rel_slug}")
+        format!("{base_path}/{lang}/{rel_slug}")
     }
 }
 
-fn output_path_for(out_dir: &Path, lang: &str, rel_slug: &str) -> PathBuf {
+/// `--base-path` wins over `base_path:` in the top-level site.md, which wins
+/// over the path part of `site_url:`. The value is site-wide, so a
+/// per-language site.md cannot change it.
+fn resolve_base_path(args: &BuildArgs) -> String {
+    let configured = args.base_path.clone().or_else(|| {
+        top_level_site_value(&args.docs_dir, "base_path")
+            .or_else(|| top_level_site_value(&args.docs_dir, "site_url"))
+    });
+    normalize_base_path(configured.as_deref().unwrap_or_default())
+}
+
+/// A site-wide key (`base_path`, `default_lang`) read from `docs/site.md`
+/// alone; per-language files cannot set these.
+fn top_level_site_value(docs_dir: &Path, name: &str) -> Option<String> {
+    let content = fs::read_to_string(docs_dir.join("site.md")).ok()?;
+    site_config_lines(&content)
+        .into_iter()
+        .find(|(_, key, _)| *key == name)
+        .map(|(_, _, value)| value.to_string())
+}
+
+/// `https://org.github.io/arch/`, `arch/` and `/arch` all become `/arch`; an
+/// empty value or `/` means the site sits at the domain root.
+fn normalize_base_path(raw: &str) -> String {
+    let raw = raw.trim();
+    let path = match raw.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|idx| &rest[idx..]).unwrap_or_default(),
+        None => raw,
+    };
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
+}
+
+/// Prefix a root-relative URL (`/assets/logo.png`) with the base path; full
+/// URLs and relative paths are returned as they are.
+fn with_base_path(base_path: &str, url: &str) -> String {
+    if url.starts_with('/') && !url.starts_with("//") {
+        format!("{base_path}{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+/// Canonical form of `path` for comparing against watcher events, which
+/// report absolute paths. Works for files that no longer (or do not yet)
+/// exist by resolving the nearest existing parent.
+fn normalize_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => normalize_path(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn output_path_for(lang: &str, rel_slug: &str) -> String {
     if rel_slug == "index" {
-        out_dir.join(lang).join("index.html")
+        format!("{lang}/index.html")
     } else {
-        out_dir.join(lang).join(rel_slug).join("index.html")
+        format!("{lang}/{rel_slug}/index.html")
     }
 }
 
@@ -484,10 +1545,55 @@ fn extract_title(md: &str) -> Option<String> {
     None
 }
 
+/// Split a leading `---` (YAML) or `+++` (TOML) block off a page. A page
+/// without one gets default frontmatter and its text back unchanged.
+///
+/// A leading `---` is also a markdown horizontal rule, so it only opens
+/// frontmatter when the block closes and holds a YAML mapping; otherwise the
+/// page is taken as having none.
+fn split_frontmatter(md: &str) -> Result<(Frontmatter, &str)> {
+    let body = md.strip_prefix('\u{feff}').unwrap_or(md);
+    let Some(first) = body.lines().next() else {
+        return Ok((Frontmatter::default(), md));
+    };
+    let fence = first.trim_end();
+    if fence != "---" && fence != "+++" {
+        return Ok((Frontmatter::default(), md));
+    }
+
+    let mut offset = first.len() + 1;
+    for line in body[offset.min(body.len())..].split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let raw = &body[first.len() + 1..offset];
+            let rest = &body[(offset + line.len()).min(body.len())..];
+            let meta = if fence == "---" {
+                if raw.trim().is_empty() {
+                    Frontmatter::default()
+                } else {
+                    match serde_yaml_ng::from_str(raw) {
+                        Ok(value @ serde_yaml_ng::Value::Mapping(_)) => {
+                            serde_yaml_ng::from_value(value)?
+                        }
+                        _ => return Ok((Frontmatter::default(), md)),
+                    }
+                }
+            } else {
+                toml::from_str(raw)?
+            };
+            return Ok((meta, rest));
+        }
+        offset += line.len();
+    }
+    if fence == "---" {
+        return Ok((Frontmatter::default(), md));
+    }
+    Err(anyhow!("frontmatter opened with '{fence}' is never closed"))
+}
+
 fn title_from_slug(slug: &str) -> String {
     let last = slug.rsplit('/').next().unwrap_or(slug);
     let mut words = Vec::new();
-    for part in last.split(|c| c == '-' || c == '_') {
+    for part in last.split(['-', '_']) {
         if part.is_empty() {
             continue;
         }
@@ -515,17 +1621,50 @@ fn md_options() -> Options {
     options
 }
 
-fn markdown_to_html(md: &str) -> String {
-    markdown_to_html_with_toc(md).0
+fn markdown_to_html(md: &str, links: Option<&links::MdLinks>) -> String {
+    markdown_to_html_with_toc(md, links).0
 }
 
 /// Render markdown, giving every heading a stable `id` plus a hover anchor, and
 /// returning the `##`/`###` headings so the sidebar can show an "On this page"
 /// list. Ids are assigned from the event stream rather than by rewriting the
 /// output, so raw HTML headings inside the page (the `<h3>` in the stack cards,
-/// for instance) are left alone and never shift the numbering.
-fn markdown_to_html_with_toc(md: &str) -> (String, Vec<TocItem>) {
-    let events: Vec<Event> = MdParser::new_ext(md, md_options()).collect();
+/// for instance) are left alone and never shift the numbering. With `links`,
+/// links to `.md` sources are pointed at the pages they publish as.
+fn markdown_to_html_with_toc(md: &str, links: Option<&links::MdLinks>) -> (String, Vec<TocItem>) {
+    let events: Vec<Event> = MdParser::new_ext(md, md_options())
+        .map(|event| match (event, links) {
+            (
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }),
+                Some(links),
+            ) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: links.rewrite(dest_url),
+                title,
+                id,
+            }),
+            (
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }),
+                Some(links),
+            ) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: links.rewrite(dest_url),
+                title,
+                id,
+            }),
+            (event, _) => event,
+        })
+        .collect();
 
     let mut toc: Vec<TocItem> = Vec::new();
     let mut seen: HashMap<String, usize> = HashMap::new();
@@ -715,32 +1854,77 @@ fn split_sections(md: &str) -> (String, String, String, String) {
     (description, architecture, architecture_json, architecture_text)
 }
 
-fn start_watcher(args: BuildArgs, reload_state: Arc<AtomicU64>) {
+fn start_watcher(
+    args: BuildArgs,
+    build: SiteBuild,
+    files: SiteFiles,
+    reload_state: Option<Arc<reload::ReloadState>>,
+) {
     std::thread::spawn(move || {
-        if let Err(err) = watch_and_rebuild(args, reload_state) {
+        if let Err(err) = watch_and_rebuild(args, build, files, reload_state) {
             eprintln!("watcher error: {err}");
         }
     });
 }
 
-fn watch_and_rebuild(args: BuildArgs, reload_state: Arc<AtomicU64>) -> NotifyResult<()> {
+/// Rebuild on every change to the docs, templates or assets until the
+/// watcher goes away. `reload_state` is the dev server's, when there is one;
+/// `build --watch` has none, and its pages carry no reload script.
+fn watch_and_rebuild(
+    args: BuildArgs,
+    build: SiteBuild,
+    files: SiteFiles,
+    reload_state: Option<Arc<reload::ReloadState>>,
+) -> NotifyResult<()> {
     let (tx, rx) = mpsc::channel();
     let mut watcher = notify::recommended_watcher(tx)?;
     watcher.watch(&args.docs_dir, RecursiveMode::Recursive)?;
     watcher.watch(&args.templates_dir, RecursiveMode::Recursive)?;
+    if args.assets_dir.exists() {
+        watcher.watch(&args.assets_dir, RecursiveMode::Recursive)?;
+    }
+    let dev_reload = reload_state.is_some();
 
-    let mut pending = false;
+    // `None` after a failed rebuild: part of the output may be stale, so the
+    // next change starts over with a full build.
+    let mut build = Some(build);
+    let mut changed: Vec<PathBuf> = Vec::new();
     loop {
         match rx.recv_timeout(Duration::from_millis(200)) {
-            Ok(_) => pending = true,
+            Ok(Ok(event)) => changed.extend(event.paths),
+            Ok(Err(err)) => eprintln!("watch error: {err}"),
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                if pending {
-                    if let Err(err) = build_site(&args, true) {
-                        eprintln!("rebuild failed: {err}");
-                    } else {
-                        reload_state.store(now_millis(), Ordering::Release);
+                if !changed.is_empty() {
+                    let started = Instant::now();
+                    let out = files.staging();
+                    let result = match build.as_mut() {
+                        Some(previous) => {
+                            rebuild_changed(&args, previous, &changed, &out, dev_reload)
+                        }
+                        None => build_site(&args, &out, dev_reload).map(|fresh| {
+                            build = Some(fresh);
+                            Rebuilt::Site
+                        }),
+                    };
+                    match result {
+                        Ok(rebuilt) => {
+                            files.publish(out);
+                            println!("rebuilt in {} ms", started.elapsed().as_millis());
+                            match (&reload_state, rebuilt) {
+                                (Some(state), Rebuilt::Site) => state.succeeded(),
+                                (Some(state), Rebuilt::Styles) => state.styles_changed(),
+                                (None, _) => {}
+                            }
+                        }
+                        Err(err) => {
+                            eprintln!("rebuild failed: {err:#}");
+                            if let Some(reload_state) = &reload_state {
+                                reload_state.failed(&err);
+                            }
+                            build = None;
+                        }
                     }
-                    pending = false;
+                    changed.clear();
                 }
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => break,
@@ -749,78 +1933,120 @@ fn watch_and_rebuild(args: BuildArgs, reload_state: Arc<AtomicU64>) -> NotifyRes
     Ok(())
 }
 
-fn load_nav_groups(lang_dir: &Path, pages: &[PageMeta], current_url: &str) -> Vec<NavGroup> {
-    let nav_path = lang_dir.join("nav.md");
-    if !nav_path.exists() {
-        return Vec::new();
-    }
-
-    let content = match fs::read_to_string(&nav_path) {
-        Ok(value) => value,
-        Err(_) => return Vec::new(),
-    };
-
-    let mut page_map = std::collections::HashMap::new();
-    for page in pages {
-        page_map.insert(page.source_rel.clone(), page);
-        page_map.insert(page.rel_slug.clone(), page);
-    }
+/// One `- page.md` line from nav.md, kept with its group and line number so the
+/// sidebar and `docsgen check` read the file the same way.
+#[derive(Clone, Debug)]
+struct NavEntry {
+    group: String,
+    target: String,
+    line: usize,
+}
 
-    let mut groups: Vec<NavGroup> = Vec::new();
-    let mut current = NavGroup {
-        title: "General".to_string(),
-        items: Vec::new(),
-        open: false,
-    };
+fn parse_nav(content: &str) -> Vec<NavEntry> {
+    let mut entries = Vec::new();
+    let mut group = "General".to_string();
 
-    for line in content.lines() {
+    for (idx, line) in content.lines().enumerate() {
         let trimmed = line.trim();
         if trimmed.is_empty() {
             continue;
         }
         if trimmed.starts_with('[') && trimmed.ends_with(']') && trimmed.len() > 2 {
-            if !current.items.is_empty() {
-                groups.push(current);
-            }
-            current = NavGroup {
-                title: trimmed.trim_start_matches('[').trim_end_matches(']').trim().to_string(),
-                items: Vec::new(),
-                open: false,
-            };
+            group = trimmed.trim_start_matches('[').trim_end_matches(']').trim().to_string();
             continue;
         }
 
         if let Some(rest) = trimmed.strip_prefix('-').or_else(|| trimmed.strip_prefix('*')) {
-            let mut item = rest.trim().to_string();
-            if item.is_empty() {
+            let mut target = rest.trim().to_string();
+            if target.is_empty() {
                 continue;
             }
-            if !item.ends_with(".md") {
-                item.push_str(".md");
+            if !target.ends_with(".md") {
+                target.push_str(".md");
             }
-            let page = page_map
-                .get(&item)
-                .or_else(|| page_map.get(item.trim_end_matches(".md")));
-            if let Some(page) = page {
-                let is_active = page.url == current_url;
-                current.items.push(NavItem {
-                    title: page.title.clone(),
-                    url: page.url.clone(),
-                });
-                if is_active {
-                    current.open = true;
-                }
+            entries.push(NavEntry {
+                group: group.clone(),
+                target,
+                line: idx + 1,
+            });
+        }
+    }
+
+    entries
+}
+
+/// A nav entry may name the source file (`welcome.md`) or the slug (`index`).
+fn resolve_nav_target<'a>(pages: &'a [PageMeta], target: &str) -> Option<&'a PageMeta> {
+    pages
+        .iter()
+        .find(|page| page.source_rel == target)
+        .or_else(|| {
+            let slug = target.trim_end_matches(".md");
+            pages.iter().find(|page| page.rel_slug == slug)
+        })
+}
+
+fn load_nav_groups(lang_dir: &Path, pages: &[PageMeta]) -> Vec<NavGroup> {
+    let nav_path = lang_dir.join("nav.md");
+    if !nav_path.exists() {
+        return Vec::new();
+    }
+
+    let content = match fs::read_to_string(&nav_path) {
+        Ok(value) => value,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut groups: Vec<NavGroup> = Vec::new();
+    let mut current: Option<NavGroup> = None;
+    let mut current_header: Option<String> = None;
+
+    for entry in parse_nav(&content) {
+        // An entry under a new `[Group]` header closes the previous group.
+        // Groups whose entries all fail to resolve are dropped.
+        if current_header.as_deref() != Some(entry.group.as_str()) {
+            if let Some(group) = current.take().filter(|g| !g.items.is_empty()) {
+                groups.push(group);
             }
+            current_header = Some(entry.group.clone());
+            current = Some(NavGroup {
+                title: entry.group.clone(),
+                items: Vec::new(),
+                open: false,
+            });
+        }
+        let Some(group) = current.as_mut() else {
+            continue;
+        };
+        let page = resolve_nav_target(pages, &entry.target)
+            .filter(|page| page.state != PageState::Hidden);
+        if let Some(page) = page {
+            group.items.push(NavItem {
+                title: page.title.clone(),
+                url: page.url.clone(),
+            });
         }
     }
 
-    if !current.items.is_empty() {
-        groups.push(current);
+    if let Some(group) = current.filter(|g| !g.items.is_empty()) {
+        groups.push(group);
     }
 
     groups
 }
 
+/// The sidebar for one page: the language's groups with the one holding
+/// `current_url` expanded.
+fn open_nav_group(groups: &[NavGroup], current_url: &str) -> Vec<NavGroup> {
+    groups
+        .iter()
+        .map(|group| NavGroup {
+            open: group.items.iter().any(|item| item.url == current_url),
+            ..group.clone()
+        })
+        .collect()
+}
+
 fn load_include_order(lang_dir: &Path) -> Result<Vec<String>> {
     let template = lang_dir.join("template.md");
     if !template.exists() {
@@ -840,6 +2066,40 @@ fn load_include_order(lang_dir: &Path) -> Result<Vec<String>> {
     Ok(order)
 }
 
+/// Keys `load_site_config` understands. `docsgen check` warns about any other
+/// key, which is almost always a typo that would otherwise be ignored.
+const SITE_KEYS: [&str; 12] = [
+    "title",
+    "logo",
+    "footer",
+    "subtitle",
+    "theme",
+    "api_base",
+    "code_samples",
+    "base_path",
+    "site_url",
+    "robots",
+    "fallback_pages",
+    "default_lang",
+];
+
+/// `key: value` lines of a site.md file with their 1-based line numbers.
+/// Comments and blank lines are skipped.
+fn site_config_lines(content: &str) -> Vec<(usize, &str, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') || trimmed.is_empty() {
+                return None;
+            }
+            let (key, value) = trimmed.split_once(':')?;
+            Some((idx + 1, key.trim(), value.trim().trim_matches('"')))
+        })
+        .collect()
+}
+
 fn load_site_config(docs_dir: &Path, lang: &str) -> SiteConfig {
     let mut config = SiteConfig::default();
     let global = docs_dir.join("site.md");
@@ -850,23 +2110,28 @@ fn load_site_config(docs_dir: &Path, lang: &str) -> SiteConfig {
             continue;
         }
         if let Ok(content) = fs::read_to_string(&path) {
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if trimmed.starts_with('#') || trimmed.is_empty() {
-                    continue;
-                }
-                if let Some((key, value)) = trimmed.split_once(':') {
-                    let key = key.trim();
-                    let value = value.trim().trim_matches('"');
-                    match key {
-                        "title" => config.title = Some(value.to_string()),
-                        "logo" => config.logo = Some(value.to_string()),
-                        "footer" => config.footer = Some(value.to_string()),
-                        "subtitle" => config.subtitle = Some(value.to_string()),
-                        "theme" => config.theme = Some(value.to_lowercase()),
-                        "api_base" => config.api_base = Some(value.to_string()),
-                        _ => {}
+            for (_, key, value) in site_config_lines(&content) {
+                match key {
+                    "title" => config.title = Some(value.to_string()),
+                    "logo" => config.logo = Some(value.to_string()),
+                    "footer" => config.footer = Some(value.to_string()),
+                    "subtitle" => config.subtitle = Some(value.to_string()),
+                    "theme" => config.theme = Some(value.to_lowercase()),
+                    "api_base" => config.api_base = Some(value.to_string()),
+                    "code_samples" => {
+                        config.code_samples = Some(
+                            value
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|name| !name.is_empty())
+                                .map(String::from)
+                                .collect(),
+                        )
                     }
+                    "site_url" => config.site_url = Some(value.to_string()),
+                    "robots" => config.robots = Some(value.to_lowercase()),
+                    "fallback_pages" => config.fallback_pages = value.eq_ignore_ascii_case("true"),
+                    _ => {}
                 }
             }
         }
@@ -885,25 +2150,31 @@ fn resolve_theme(requested: Option<&str>) -> String {
         return name.to_string();
     }
 
-    // load_site_config runs once per page, so warn only once per bad value.
+    // Every language reads the global site.md, and `serve --watch` reloads
+    // it on each rebuild, so warn only once per bad value.
     static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
     let warned = WARNED.get_or_init(|| Mutex::new(HashSet::new()));
-    if let Ok(mut seen) = warned.lock() {
-        if seen.insert(name.to_string()) {
-            eprintln!(
-                "warning: unknown theme '{name}' in site.md; using '{DEFAULT_THEME}'. Available: {}",
-                THEMES.join(", ")
-            );
-        }
+    if let Ok(mut seen) = warned.lock()
+        && seen.insert(name.to_string())
+    {
+        eprintln!(
+            "warning: unknown theme '{name}' in site.md; using '{DEFAULT_THEME}'. Available: {}",
+            THEMES.join(", ")
+        );
     }
     DEFAULT_THEME.to_string()
 }
 
-fn write_search_index(out_dir: &Path, entries: &[SearchEntry]) -> Result<()> {
-    let path = out_dir.join("search.json");
-    let json = serde_json::to_string(entries)?;
-    fs::write(path, json)?;
-    Ok(())
+/// Only published pages are searchable; hidden pages and the drafts `serve`
+/// renders stay out of the index.
+fn write_search_index(out: &Output, entries: &[&SearchEntry]) -> Result<()> {
+    let listed: Vec<&SearchEntry> = entries
+        .iter()
+        .copied()
+        .filter(|entry| entry.state == PageState::Published)
+        .collect();
+    let json = serde_json::to_string(&listed)?;
+    out.write("search.json", json)
 }
 
 fn markdown_to_text(md: &str) -> String {
@@ -921,6 +2192,10 @@ fn markdown_to_text(md: &str) -> String {
         if trimmed.starts_with("@include:") {
             continue;
         }
+        // `import openapi` region markers.
+        if trimmed.starts_with("<!--") && trimmed.ends_with("-->") {
+            continue;
+        }
         let cleaned = trimmed
             .trim_start_matches('#')
             .trim_start_matches('*')
@@ -960,10 +2235,17 @@ fn now_millis() -> u64 {
 }
 
 fn expand_includes(md: &str, base_dir: &Path) -> Result<String> {
-    expand_includes_inner(md, base_dir, 0)
+    expand_includes_tracked(md, base_dir, 0, &mut Vec::new())
 }
 
-fn expand_includes_inner(md: &str, base_dir: &Path, depth: usize) -> Result<String> {
+/// `expand_includes`, also recording every file it reads (or tries to) in
+/// `deps`, so a watch rebuild knows which pages an include feeds.
+fn expand_includes_tracked(
+    md: &str,
+    base_dir: &Path,
+    depth: usize,
+    deps: &mut Vec<PathBuf>,
+) -> Result<String> {
     if depth > 5 {
         return Err(anyhow!("include depth exceeded"));
     }
@@ -976,9 +2258,14 @@ fn expand_includes_inner(md: &str, base_dir: &Path, depth: usize) -> Result<Stri
                 continue;
             }
             let target = base_dir.join(rel);
+            deps.push(target.clone());
             let included = fs::read_to_string(&target)
                 .with_context(|| format!("failed to read include {}", target.display()))?;
-            let expanded = expand_includes_inner(&included, base_dir, depth + 1)?;
+            // An included page's frontmatter describes that page, not this one.
+            let included = split_frontmatter(&included)
+                .map(|(_, body)| body)
+                .unwrap_or(&included);
+            let expanded = expand_includes_tracked(included, base_dir, depth + 1, deps)?;
             out.push_str(&expanded);
             out.push('\n');
         } else {
@@ -999,6 +2286,7 @@ fn neighbour_pages(
     let ordered: Vec<NavItem> = if groups.is_empty() {
         pages
             .iter()
+            .filter(|page| page.state != PageState::Hidden)
             .map(|page| NavItem {
                 title: page.title.clone(),
                 url: page.url.clone(),
@@ -1020,6 +2308,32 @@ fn neighbour_pages(
     (prev, ordered.get(idx + 1).cloned())
 }
 
+/// One entry per language, matching pages by `rel_slug`. A missing page links
+/// to the language root with `?untranslated` so it can say why the reader
+/// landed there.
+fn translations_for(site: &SiteMeta, current_lang: &str, rel_slug: &str) -> Vec<Translation> {
+    site.langs
+        .iter()
+        .map(|lang| {
+            let found = lang.pages.iter().find(|page| page.rel_slug == rel_slug);
+            let (url, state) = match found {
+                Some(page) if lang.code == current_lang => (page.url.clone(), TranslationState::Current),
+                Some(page) if page.fallback_from.is_some() => (page.url.clone(), TranslationState::Fallback),
+                Some(page) => (page.url.clone(), TranslationState::Translated),
+                None => (
+                    format!("{}?untranslated", url_for(&site.base_path, &lang.code, "index")),
+                    TranslationState::Missing,
+                ),
+            };
+            Translation {
+                code: lang.code.clone(),
+                url,
+                state,
+            }
+        })
+        .collect()
+}
+
 fn breadcrumb_for(groups: &[NavGroup], current_url: &str) -> Option<String> {
     groups
         .iter()
@@ -1029,35 +2343,150 @@ fn breadcrumb_for(groups: &[NavGroup], current_url: &str) -> Option<String> {
 
 // ─── API blocks ──────────────────────────────────────────────────────────────
 
-/// Turn the `### JSON` section into request / response / curl cards.
+/// Turn the `### JSON` section into request / response / code sample cards,
+/// grouped by endpoint.
 ///
 /// Preferred form is one `#### ` subheading per block (`#### Request POST /v1/x`,
 /// `#### Response 200`, `#### cURL`). Pages written before that existed — a
 /// single JSON object with `request` / `response` / `*_error` keys — are split
 /// on those top-level keys instead, so they render as separate cards untouched.
 /// Anything else returns empty and the caller falls back to plain markdown.
-fn build_api_blocks(json_md: &str, api_base: &str) -> Vec<ApiBlock> {
-    let mut blocks = parse_api_blocks(json_md).unwrap_or_else(|| legacy_api_blocks(json_md));
-    if blocks.is_empty() {
-        return blocks;
-    }
-
-    // A request that names a method and path is enough to write the curl call
-    // for the author; an explicit `#### cURL` block always wins.
-    if !blocks.iter().any(|block| block.kind == "curl") {
-        if let Some(idx) = blocks.iter().position(|block| block.kind == "request") {
-            if let Some(curl) = synth_curl(&blocks[idx].raw, api_base) {
-                let block = build_block(
-                    "curl", "cURL", None, None, None, None, "bash", &curl, true,
-                );
-                blocks.insert(idx + 1, block);
+fn build_api_endpoints(
+    json_md: &str,
+    api_base: &str,
+    languages: &[&samples::Generator],
+) -> Vec<ApiEndpoint> {
+    let blocks = parse_api_blocks(json_md).unwrap_or_else(|| legacy_api_blocks(json_md));
+
+    // Each request opens an endpoint that runs up to the next one.
+    let mut groups: Vec<Vec<ApiBlock>> = Vec::new();
+    for block in blocks {
+        if block.kind == "request" || groups.is_empty() {
+            groups.push(Vec::new());
+        }
+        if let Some(group) = groups.last_mut() {
+            group.push(block);
+        }
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    groups
+        .into_iter()
+        .map(|group| {
+            let blocks = with_code_samples(group, api_base, languages);
+            let Some(request) = blocks.first().filter(|block| block.kind == "request") else {
+                return ApiEndpoint {
+                    id: None,
+                    method: None,
+                    path: None,
+                    label: None,
+                    blocks,
+                };
+            };
+            let name = match (&request.method, &request.path) {
+                (Some(method), Some(path)) => format!("{method} {path}"),
+                (None, Some(path)) => path.clone(),
+                _ => request.label.clone(),
+            };
+            let base = format!("endpoint-{}", slugify(&name));
+            let count = seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            let id = if *count == 1 {
+                base
+            } else {
+                format!("{base}-{count}")
+            };
+            ApiEndpoint {
+                id: Some(id),
+                method: request.method.clone(),
+                path: request.path.clone(),
+                label: Some(request.label.clone()).filter(|label| label != "Request"),
+                blocks,
             }
+        })
+        .collect()
+}
+
+/// Every card of every endpoint, in page order.
+fn build_api_blocks(
+    json_md: &str,
+    api_base: &str,
+    languages: &[&samples::Generator],
+) -> Vec<ApiBlock> {
+    build_api_endpoints(json_md, api_base, languages)
+        .into_iter()
+        .flat_map(|endpoint| endpoint.blocks)
+        .collect()
+}
+
+/// Add the tabbed code sample card to one endpoint's cards.
+fn with_code_samples(
+    blocks: Vec<ApiBlock>,
+    api_base: &str,
+    languages: &[&samples::Generator],
+) -> Vec<ApiBlock> {
+    // Authored samples leave their place in the endpoint and join the tabbed
+    // card, where each one stands in for that language's generated sample.
+    let is_sample = |block: &ApiBlock| samples::find(&block.kind).is_some();
+    let first_authored = blocks.iter().position(is_sample);
+    let (mut authored, mut blocks): (Vec<ApiBlock>, Vec<ApiBlock>) =
+        blocks.into_iter().partition(is_sample);
+
+    // A request that names a method and path is enough to write the call in
+    // every configured language for the author.
+    let request = blocks.iter().position(|block| block.kind == "request");
+    let parsed = request.and_then(|idx| samples::Request::parse(&blocks[idx].raw, api_base));
+
+    let mut tabs = Vec::new();
+    for generator in languages {
+        if let Some(idx) = authored.iter().position(|block| block.kind == generator.id) {
+            tabs.push(authored.remove(idx));
+        } else if let Some(request) = &parsed {
+            let code = generator.generate(request);
+            tabs.push(build_block(
+                generator.id,
+                generator.label,
+                None,
+                None,
+                None,
+                None,
+                generator.lang,
+                &code,
+                true,
+            ));
         }
     }
+    tabs.extend(authored);
+
+    let at = match (request, first_authored) {
+        (Some(idx), _) if parsed.is_some() => Some(idx + 1),
+        (_, Some(idx)) => Some(idx),
+        _ => None,
+    };
+    if let Some(at) = at.filter(|_| !tabs.is_empty()) {
+        blocks.insert(at, samples_card(tabs));
+    }
 
     blocks
 }
 
+fn samples_card(tabs: Vec<ApiBlock>) -> ApiBlock {
+    ApiBlock {
+        kind: "samples".to_string(),
+        label: "Code samples".to_string(),
+        method: None,
+        path: None,
+        status: None,
+        status_class: String::new(),
+        note: None,
+        lang: String::new(),
+        code_html: String::new(),
+        generated: tabs.iter().all(|tab| tab.generated),
+        samples: tabs,
+        raw: String::new(),
+    }
+}
+
 /// Returns `None` when the section has no `#### ` subheadings at all, which is
 /// how the caller knows to try the legacy split instead.
 fn parse_api_blocks(md: &str) -> Option<Vec<ApiBlock>> {
@@ -1244,6 +2673,7 @@ fn build_block(
         lang: lang.to_string(),
         code_html,
         generated,
+        samples: Vec::new(),
         raw: code,
     }
 }
@@ -1273,12 +2703,24 @@ fn parse_api_heading(
     let Some(first) = tokens.next() else {
         return ("other".into(), "Details".into(), None, None, None);
     };
+    // `#### Python`, `#### Go`: a language on its own. Only cURL takes a
+    // label after it, so "Go live" stays an ordinary heading, and the
+    // `code_samples:` short names (`Requests`, `Bash`) are not languages here.
+    if let Some(generator) = samples::for_heading(text) {
+        return (
+            generator.id.into(),
+            generator.label.into(),
+            None,
+            None,
+            None,
+        );
+    }
 
     let (kind, default_label) = match first.trim_end_matches(':').to_lowercase().as_str() {
         "request" | "req" => ("request", "Request"),
         "response" | "resp" => ("response", "Response"),
         "error" | "errors" => ("response", "Error"),
-        "curl" | "bash" | "shell" => ("curl", "cURL"),
+        "curl" => ("curl", "cURL"),
         _ => ("other", ""),
     };
     if kind == "other" {
@@ -1492,7 +2934,7 @@ fn dedent_body(src: &str) -> String {
 }
 
 fn humanize_key(key: &str) -> String {
-    key.split(|c: char| c == '_' || c == '-' || c == ' ')
+    key.split(['_', '-', ' '])
         .filter(|part| !part.is_empty())
         .map(|part| {
             let mut chars = part.chars();
@@ -1505,81 +2947,6 @@ fn humanize_key(key: &str) -> String {
         .join(" ")
 }
 
-/// Build a runnable curl call from a request block shaped like
-/// `{ "method": ..., "path": ..., "headers": {...}, "body": {...} }`.
-/// Returns `None` when there is no path to call.
-fn synth_curl(request_raw: &str, api_base: &str) -> Option<String> {
-    let fields = split_top_level_json_object(request_raw);
-    if fields.is_empty() {
-        return None;
-    }
-    let field = |name: &str| {
-        fields
-            .iter()
-            .find(|(key, _)| key == name)
-            .map(|(_, value)| value.clone())
-    };
-
-    let path = field("path").or_else(|| field("url")).map(|v| unquote(&v))?;
-    if path.is_empty() {
-        return None;
-    }
-    let method = field("method")
-        .map(|v| unquote(&v).to_uppercase())
-        .unwrap_or_else(|| "GET".to_string());
-
-    let url = if path.starts_with("http://") || path.starts_with("https://") {
-        path
-    } else if path.starts_with('/') {
-        format!("{api_base}{path}")
-    } else {
-        format!("{api_base}/{path}")
-    };
-
-    let body = field("body").or_else(|| field("payload"));
-
-    let mut headers: Vec<String> = Vec::new();
-    if let Some(raw) = field("headers") {
-        for (key, value) in split_top_level_json_object(&raw) {
-            headers.push(format!("{key}: {}", unquote(&value)));
-        }
-    }
-    if body.is_some()
-        && !headers
-            .iter()
-            .any(|header| header.to_lowercase().starts_with("content-type:"))
-    {
-        headers.push("Content-Type: application/json".to_string());
-    }
-
-    let mut out = format!("curl -X {method} {url}");
-    for header in headers {
-        out.push_str(" \\\n  -H \"");
-        out.push_str(&header);
-        out.push('"');
-    }
-    if let Some(body) = body {
-        let body = dedent_body(&body);
-        let indented = body
-            .lines()
-            .enumerate()
-            .map(|(idx, line)| {
-                if idx == 0 {
-                    line.to_string()
-                } else {
-                    format!("  {line}")
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        out.push_str(" \\\n  -d '");
-        out.push_str(&indented);
-        out.push('\'');
-    }
-
-    Some(out)
-}
-
 // ─── Highlighting ────────────────────────────────────────────────────────────
 
 fn escape_html(src: &str) -> String {
@@ -1743,31 +3110,46 @@ fn highlight_shell(src: &str) -> String {
     out
 }
 
-fn copy_assets(src_dir: &Path, dest_dir: &Path) -> Result<()> {
+/// Bring one changed path under `assets_dir` up to date the way
+/// `copy_assets` would have copied it: a file is copied, a directory copied
+/// whole, and a path that no longer exists removed from the output.
+fn sync_asset(assets_dir: &Path, path: &Path, out: &Output) -> Result<()> {
+    let Ok(rel) = path.strip_prefix(assets_dir) else {
+        return Ok(());
+    };
+    let rel = rel.to_string_lossy().replace('\\', "/");
+    let dest = match rel.as_str() {
+        "" => "assets".to_string(),
+        rel => format!("assets/{rel}"),
+    };
+    if path.is_dir() {
+        copy_assets(path, out, &dest)
+    } else if path.is_file() {
+        let contents =
+            fs::read(path).with_context(|| format!("failed to copy {}", path.display()))?;
+        out.write(&dest, contents)
+    } else {
+        out.remove(&dest)
+    }
+}
+
+fn copy_assets(src_dir: &Path, out: &Output, dest: &str) -> Result<()> {
     if !src_dir.exists() {
         return Ok(());
     }
-    fs::create_dir_all(dest_dir)
-        .with_context(|| format!("failed to create {}", dest_dir.display()))?;
 
     for entry in WalkDir::new(src_dir)
         .follow_links(false)
         .into_iter()
         .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
     {
         let path = entry.path();
         let rel = path.strip_prefix(src_dir)?;
-        let target = dest_dir.join(rel);
-        if entry.file_type().is_dir() {
-            fs::create_dir_all(&target)
-                .with_context(|| format!("failed to create {}", target.display()))?;
-        } else {
-            if let Some(parent) = target.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::copy(path, &target)
-                .with_context(|| format!("failed to copy {}", path.display()))?;
-        }
+        let contents =
+            fs::read(path).with_context(|| format!("failed to copy {}", path.display()))?;
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        out.write(&format!("{dest}/{rel}"), contents)?;
     }
     Ok(())
 }
@@ -1775,6 +3157,7 @@ fn copy_assets(src_dir: &Path, dest_dir: &Path) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixture::TempDir;
 
     #[test]
     fn sections_keep_content_written_after_architecture() {
@@ -1816,6 +3199,18 @@ mod tests {
         assert_eq!((kind.as_str(), label.as_str()), ("other", "Webhook payload"));
     }
 
+    #[test]
+    fn api_heading_names_a_language_by_its_label_only() {
+        let languages = [("Python", "python"), ("javascript", "javascript"), ("cURL", "curl")];
+        for (heading, id) in languages {
+            assert_eq!(parse_api_heading(heading).0, id, "{heading}");
+        }
+        for heading in ["Requests", "Fetch", "Shell", "Bash", "Go live"] {
+            let (kind, label, ..) = parse_api_heading(heading);
+            assert_eq!((kind.as_str(), label.as_str()), ("other", heading));
+        }
+    }
+
     #[test]
     fn top_level_split_preserves_key_order_and_formatting() {
         let src = "{\n  \"b\": {\n    \"nested\": \"x, y\"\n  },\n  \"a\": [1, 2],\n  \"c\": \"done\"\n}";
@@ -1829,40 +3224,101 @@ mod tests {
     #[test]
     fn legacy_single_object_splits_into_cards() {
         let md = "```json\n{\n  \"request\": {\n    \"method\": \"POST\",\n    \"path\": \"/v1/x\"\n  },\n  \"response\": { \"ok\": true },\n  \"not_active_error\": { \"status\": 403 }\n}\n```\n";
-        let blocks = build_api_blocks(md, "https://api.test");
+        let blocks = build_api_blocks(md, "https://api.test", &samples::languages(None));
         let kinds: Vec<&str> = blocks.iter().map(|b| b.kind.as_str()).collect();
-        assert_eq!(kinds, vec!["request", "curl", "response", "response"]);
+        assert_eq!(kinds, vec!["request", "samples", "response", "response"]);
         assert_eq!(blocks[0].method.as_deref(), Some("POST"));
         assert_eq!(blocks[3].label, "Not Active Error");
         assert_eq!(blocks[3].status.as_deref(), Some("403"));
         assert_eq!(blocks[3].status_class, "warn");
         assert!(blocks[1].generated);
+        assert_eq!(blocks[1].samples.len(), samples::GENERATORS.len());
     }
 
     #[test]
     fn plain_json_object_is_left_to_the_markdown_renderer() {
         let md = "```json\n{ \"metrics\": \"Prometheus\" }\n```\n";
-        assert!(build_api_blocks(md, "https://api.test").is_empty());
+        assert!(build_api_blocks(md, "https://api.test", &samples::languages(None)).is_empty());
     }
 
     #[test]
     fn authored_curl_block_suppresses_the_generated_one() {
         let md = "#### Request POST /v1/x\n\n```json\n{ \"method\": \"POST\", \"path\": \"/v1/x\" }\n```\n\n#### cURL\n\n```bash\ncurl mine\n```\n";
-        let blocks = build_api_blocks(md, "https://api.test");
+        let curl_only = [samples::find("curl").unwrap()];
+        let blocks = build_api_blocks(md, "https://api.test", &curl_only);
         assert_eq!(blocks.len(), 2);
-        assert!(!blocks.iter().any(|b| b.generated));
+        assert_eq!(blocks[1].samples.len(), 1);
+        assert!(!blocks.iter().flat_map(|b| &b.samples).any(|b| b.generated));
+    }
+
+    #[test]
+    fn authored_samples_replace_their_language_in_the_tabbed_card() {
+        let md = "#### Request GET /v1/x\n\n```json\n{ \"path\": \"/v1/x\" }\n```\n\n#### Response 200\n\n```json\n{}\n```\n\n#### Python\n\n```python\nclient.get(\"x\")\n```\n\n#### Ruby\n\n```ruby\nx\n```\n";
+        let languages = samples::languages(Some(&["curl".to_string(), "py".to_string()]));
+        let blocks = build_api_blocks(md, "https://api.test", &languages);
+        let kinds: Vec<&str> = blocks.iter().map(|b| b.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["request", "samples", "response", "other"]);
+        let tabs: Vec<(&str, bool)> = blocks[1]
+            .samples
+            .iter()
+            .map(|b| (b.label.as_str(), b.generated))
+            .collect();
+        assert_eq!(tabs, vec![("cURL", true), ("Python", false)]);
+        assert!(blocks[1].samples[1].raw.contains("client.get"));
+    }
+
+    #[test]
+    fn each_request_opens_an_endpoint_with_its_own_samples() {
+        let md = concat!(
+            "#### Response 429 — Rate limited\n\n```json\n{}\n```\n\n",
+            "#### Request GET /v1/x\n\n```json\n{ \"path\": \"/v1/x\" }\n```\n\n",
+            "#### Response 200\n\n```json\n{}\n```\n\n",
+            "#### Request POST /v1/x — Create one\n\n```json\n{ \"method\": \"POST\", \"path\": \"/v1/x\" }\n```\n\n",
+            "#### cURL\n\n```bash\ncurl mine\n```\n\n",
+            "#### Response 201\n\n```json\n{}\n```\n\n",
+            "#### Request GET /v1/x\n\n```json\n{ \"path\": \"/v1/x?page=2\" }\n```\n",
+        );
+        let curl_only = [samples::find("curl").unwrap()];
+        let endpoints = build_api_endpoints(md, "https://api.test", &curl_only);
+        let ids: Vec<Option<&str>> = endpoints.iter().map(|e| e.id.as_deref()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                None,
+                Some("endpoint-get-v1-x"),
+                Some("endpoint-post-v1-x"),
+                Some("endpoint-get-v1-x-2"),
+            ]
+        );
+        let kinds = |idx: usize| -> Vec<&str> {
+            endpoints[idx].blocks.iter().map(|b| b.kind.as_str()).collect()
+        };
+        assert_eq!(kinds(0), vec!["response"]);
+        assert_eq!(kinds(1), vec!["request", "samples", "response"]);
+        assert_eq!(kinds(2), vec!["request", "samples", "response"]);
+        assert_eq!(endpoints[1].label, None);
+        assert_eq!(endpoints[2].label.as_deref(), Some("Create one"));
+
+        let curl = |idx: usize| &endpoints[idx].blocks[1].samples[0];
+        assert!(curl(1).generated && curl(1).raw.starts_with("curl -X GET https://api.test/v1/x"));
+        assert!(!curl(2).generated && curl(2).raw == "curl mine");
+        assert!(curl(3).generated && curl(3).raw.contains("page=2"));
     }
 
     #[test]
     fn generated_curl_carries_headers_and_body() {
+        let curl = |request: &str| {
+            samples::Request::parse(request, "https://api.test")
+                .map(|request| samples::find("curl").unwrap().generate(&request))
+        };
         let request = "{\n  \"method\": \"post\",\n  \"path\": \"/v1/x\",\n  \"headers\": { \"APIKEY\": \"k\" },\n  \"body\": { \"a\": 1 }\n}";
-        let curl = synth_curl(request, "https://api.test").expect("curl");
-        assert!(curl.starts_with("curl -X POST https://api.test/v1/x"));
-        assert!(curl.contains("-H \"APIKEY: k\""));
-        assert!(curl.contains("-H \"Content-Type: application/json\""), "json body without content-type");
-        assert!(curl.contains("-d '{ \"a\": 1 }'"));
+        let curl_call = curl(request).expect("curl");
+        assert!(curl_call.starts_with("curl -X POST https://api.test/v1/x"));
+        assert!(curl_call.contains("-H \"APIKEY: k\""));
+        assert!(curl_call.contains("-H \"Content-Type: application/json\""), "json body without content-type");
+        assert!(curl_call.contains("-d '{ \"a\": 1 }'"));
 
-        assert!(synth_curl("{ \"method\": \"GET\" }", "https://api.test").is_none(), "no path should mean no curl");
+        assert!(curl("{ \"method\": \"GET\" }").is_none(), "no path should mean no curl");
     }
 
     #[test]
@@ -1874,9 +3330,408 @@ mod tests {
         assert!(out.contains("<span class=\"tok-lit\">true</span>"));
     }
 
+    #[test]
+    fn frontmatter_is_split_off_in_yaml_or_toml() {
+        let (meta, body) = split_frontmatter(
+            "---\ntitle: Tokens\norder: 2\ntags: [auth, keys]\nowner: platform\n---\n# Ignored\n",
+        )
+        .unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Tokens"));
+        assert_eq!(meta.order, Some(2));
+        assert_eq!(meta.tags, vec!["auth", "keys"]);
+        assert_eq!(meta.extra["owner"], "platform");
+        assert_eq!(body, "# Ignored\n");
+
+        let (meta, body) = split_frontmatter("+++\nslug = \"start\"\ndraft = true\n+++\nbody").unwrap();
+        assert_eq!(meta.slug.as_deref(), Some("start"));
+        assert!(meta.draft);
+        assert_eq!(body, "body");
+
+        let (meta, body) = split_frontmatter("# Plain\n\n---\n").unwrap();
+        assert!(meta.title.is_none());
+        assert_eq!(body, "# Plain\n\n---\n");
+        assert!(split_frontmatter("+++\ntitle = \"x\"\n").is_err());
+        let (_, body) = split_frontmatter("---\ntitle: x\n").unwrap();
+        assert_eq!(body, "---\ntitle: x\n");
+    }
+
+    #[test]
+    fn config_files_are_skipped_before_they_are_read_as_pages() {
+        let root = TempDir::new(
+            "config-files",
+            &[
+                ("en/welcome.md", "# Welcome\n"),
+                ("en/nav.md", "+++\n- [Welcome](welcome.md)\n"),
+            ],
+        );
+        let pages = collect_pages_for_lang(&root.join("en"), "en", false, "").unwrap();
+        let sources: Vec<&str> = pages.iter().map(|page| page.source_rel.as_str()).collect();
+        assert_eq!(sources, ["welcome.md"]);
+    }
+
+    #[test]
+    fn titles_expand_includes_next_to_the_page() {
+        let root = TempDir::new(
+            "title-includes",
+            &[
+                ("en/guides/setup.md", "@include: _heading.md\n\nSteps.\n"),
+                ("en/guides/_heading.md", "# Setting up\n"),
+                ("en/broken.md", "@include: missing.md\n\n# Still titled\n"),
+            ],
+        );
+        let pages = collect_pages_for_lang(&root.join("en"), "en", false, "").unwrap();
+        let title = |source: &str| {
+            let page = pages.iter().find(|page| page.source_rel == source).unwrap();
+            page.title.clone()
+        };
+        assert_eq!(title("guides/setup.md"), "Setting up");
+        assert_eq!(title("broken.md"), "Still titled");
+    }
+
+    #[test]
+    fn a_leading_horizontal_rule_is_not_frontmatter() {
+        for md in [
+            "---\n\nIntro under a rule.\n",
+            "---\n\nIntro.\n\n---\n\n# Setup\n",
+            "---\n- one\n- two\n---\n",
+        ] {
+            let (meta, body) = split_frontmatter(md).unwrap();
+            assert!(meta.title.is_none());
+            assert_eq!(body, md);
+        }
+        assert!(split_frontmatter("---\ntitle: [Tokens, Keys]\n---\n").is_err());
+    }
+
+    #[test]
+    fn base_path_is_normalised_from_a_path_or_a_full_url() {
+        assert_eq!(normalize_base_path(""), "");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("docs/"), "/docs");
+        assert_eq!(normalize_base_path("https://org.github.io/arch/"), "/arch");
+        assert_eq!(normalize_base_path("https://docs.example.com"), "");
+        assert_eq!(url_for("/arch", "en", "index"), "/arch/en/");
+        assert_eq!(with_base_path("/arch", "/assets/logo.png"), "/arch/assets/logo.png");
+        assert_eq!(with_base_path("/arch", "https://cdn.test/logo.svg"), "https://cdn.test/logo.svg");
+    }
+
+    #[test]
+    fn file_names_mark_drafts_and_hidden_pages() {
+        assert_eq!(
+            state_from_file_name("guides/setup.draft"),
+            ("guides/setup".to_string(), PageState::Draft)
+        );
+        assert_eq!(state_from_file_name("_internal"), ("internal".to_string(), PageState::Hidden));
+        assert_eq!(state_from_file_name("errors"), ("errors".to_string(), PageState::Published));
+    }
+
+    #[test]
+    fn hidden_pages_are_left_out_of_the_pager() {
+        let page = |slug: &str, state| PageMeta {
+            title: slug.to_string(),
+            url: format!("/en/{slug}"),
+            rel_slug: slug.to_string(),
+            state,
+            ..PageMeta::default()
+        };
+        let pages = vec![
+            page("a", PageState::Published),
+            page("b", PageState::Hidden),
+            page("c", PageState::Published),
+        ];
+        let (prev, next) = neighbour_pages(&[], &pages, "/en/a");
+        assert!(prev.is_none());
+        assert_eq!(next.map(|item| item.url).as_deref(), Some("/en/c"));
+    }
+
+    #[test]
+    fn language_switch_targets_the_same_page_or_the_language_root() {
+        let lang = |code: &str, slugs: &[&str]| LangMeta {
+            code: code.to_string(),
+            pages: slugs
+                .iter()
+                .map(|slug| PageMeta {
+                    url: url_for("", code, slug),
+                    rel_slug: slug.to_string(),
+                    ..PageMeta::default()
+                })
+                .collect(),
+        };
+        let site = SiteMeta {
+            langs: vec![lang("en", &["index", "errors"]), lang("hi", &["index"])],
+            default_lang: "en".to_string(),
+            base_path: String::new(),
+        };
+        let found: Vec<(String, String, TranslationState)> = translations_for(&site, "en", "errors")
+            .into_iter()
+            .map(|t| (t.code, t.url, t.state))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("en".to_string(), "/en/errors".to_string(), TranslationState::Current),
+                ("hi".to_string(), "/hi/?untranslated".to_string(), TranslationState::Missing),
+            ]
+        );
+        let hi = translations_for(&site, "hi", "index");
+        assert_eq!(hi[0].state, TranslationState::Translated);
+        assert_eq!(hi[0].url, "/en/");
+    }
+
+    #[test]
+    fn fallback_pages_fill_gaps_at_the_other_language_urls() {
+        let page = |lang: &str, slug: &str| PageMeta {
+            url: url_for("", lang, slug),
+            rel_slug: slug.to_string(),
+            source_rel: format!("{slug}.md"),
+            ..PageMeta::default()
+        };
+        let mut hi = LangMeta {
+            code: "hi".to_string(),
+            pages: vec![page("hi", "errors")],
+        };
+        let en = [page("en", "index"), page("en", "errors"), page("en", "limits")];
+        add_fallback_pages(&mut hi, &en, "en", "");
+
+        let found: Vec<(&str, Option<&str>)> = hi
+            .pages
+            .iter()
+            .map(|p| (p.url.as_str(), p.fallback_from.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("/hi/", Some("en")), ("/hi/errors", None), ("/hi/limits", Some("en"))]
+        );
+        assert_eq!(hi.pages[2].source_rel, "limits.md");
+    }
+
+    #[test]
+    fn accept_language_picks_the_best_available_language() {
+        let available = ["en", "hi", "pt-BR"];
+        assert_eq!(negotiate_lang("hi-IN,hi;q=0.9,en;q=0.8", &available), Some("hi"));
+        assert_eq!(negotiate_lang("fr, en;q=0.5, hi;q=0.7", &available), Some("hi"));
+        assert_eq!(negotiate_lang("pt-PT", &available), Some("pt-BR"));
+        assert_eq!(negotiate_lang("hi;q=0, en;q=0.1", &available), Some("en"));
+        assert_eq!(negotiate_lang("fr-FR, *", &available), None);
+        assert_eq!(negotiate_lang("", &available), None);
+    }
+
+    #[test]
+    fn lang_route_only_matches_known_languages() {
+        let lang = |code: &str| LangMeta {
+            code: code.to_string(),
+            pages: Vec::new(),
+        };
+        let site = SiteMeta {
+            langs: vec![lang("en"), lang("pt.br")],
+            default_lang: "en".to_string(),
+            base_path: String::new(),
+        };
+        let route = actix_web::dev::ResourceDef::new(lang_route_pattern(&site));
+        assert!(route.is_match("/en"));
+        assert!(route.is_match("/pt.br"));
+        assert!(!route.is_match("/ptxbr"));
+        assert!(!route.is_match("/typo"));
+        assert!(!route.is_match("/search.json"));
+    }
+
+    /// Build args for a fixture under `root/docs` (and `root/assets`, if
+    /// any), writing to `root/{out}`.
+    fn fixture_build_args(root: &Path, out: &str, extra: &[&str]) -> BuildArgs {
+        let docs = root.join("docs");
+        let out = root.join(out);
+        let assets = root.join("assets");
+        let mut argv = vec![
+            "build",
+            "--docs-dir",
+            docs.to_str().unwrap(),
+            "--out-dir",
+            out.to_str().unwrap(),
+            "--templates-dir",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/templates"),
+            "--assets-dir",
+            assets.to_str().unwrap(),
+        ];
+        argv.extend_from_slice(extra);
+        BuildArgs::parse_from(argv)
+    }
+
+    fn disk_output(args: &BuildArgs) -> Output {
+        Output::disk(&args.out_dir, false)
+    }
+
+    /// Every file under `dir`, keyed by its relative path.
+    fn output_files(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let rel = entry.path().strip_prefix(dir).unwrap().display().to_string();
+                (rel, fs::read(entry.path()).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_build_matches_a_single_threaded_one() {
+        let root = TempDir::new("parallel", &[]);
+        for lang in ["en", "hi"] {
+            let mut nav = String::from("[Pages]\n");
+            for n in 0..40 {
+                let body = format!("# Page {n}\n\nSee [the next one](page-{}.md).\n", n + 1);
+                root.write(&format!("docs/{lang}/page-{n}.md"), body);
+                nav.push_str(&format!("- page-{n}.md\n"));
+            }
+            root.write(&format!("docs/{lang}/nav.md"), nav);
+        }
+
+        let serial_args = fixture_build_args(&root, "serial", &["--jobs", "1"]);
+        let parallel_args = fixture_build_args(&root, "parallel", &["--jobs", "8"]);
+        let serial = build_site(&serial_args, &disk_output(&serial_args), false);
+        let parallel = build_site(&parallel_args, &disk_output(&parallel_args), false);
+        let serial_urls: Vec<String> = serial.unwrap().pages.into_iter().map(|p| p.url).collect();
+        let parallel_urls: Vec<String> = parallel.unwrap().pages.into_iter().map(|p| p.url).collect();
+        assert_eq!(serial_urls.len(), 80);
+        assert_eq!(serial_urls, parallel_urls);
+        assert_eq!(output_files(&root.join("serial")), output_files(&root.join("parallel")));
+    }
+
+    #[test]
+    fn memory_output_holds_the_same_files_as_a_disk_build() {
+        let root = TempDir::new(
+            "memory",
+            &[
+                ("docs/site.md", "site_url: https://example.com\n"),
+                ("docs/en/welcome.md", "# Welcome\n\nSee [errors](errors.md).\n"),
+                ("docs/en/errors.md", "# Errors\n"),
+                ("docs/hi/welcome.md", "# स्वागत\n"),
+                ("assets/css/app.css", "body{}"),
+            ],
+        );
+        let args = fixture_build_args(&root, "public", &[]);
+        build_site(&args, &disk_output(&args), false).unwrap();
+        let memory = Output::Memory(Mutex::default());
+        build_site(&args, &memory, false).unwrap();
+
+        let mut on_disk = output_files(&args.out_dir);
+        assert!(on_disk.remove(".docsgen").is_some());
+        let in_memory: BTreeMap<String, Vec<u8>> = memory
+            .into_files()
+            .into_iter()
+            .map(|(rel, body)| (rel, body.to_vec()))
+            .collect();
+        let manifest: BTreeMap<String, String> =
+            serde_json::from_slice(&in_memory["assets-manifest.json"]).unwrap();
+        assert!(in_memory.contains_key(&format!("assets/{}", manifest["css/app.css"])));
+        assert!(in_memory.contains_key("sitemap.xml"));
+        assert_eq!(in_memory, on_disk);
+    }
+
+    #[test]
+    fn a_static_build_points_pages_at_fingerprinted_assets() {
+        let root = TempDir::new(
+            "fingerprint",
+            &[
+                ("docs/site.md", "logo: /assets/logo.svg\n"),
+                ("docs/en/welcome.md", "# Welcome\n"),
+                ("assets/app.css", "body{}"),
+                ("assets/logo.svg", "<svg/>"),
+            ],
+        );
+        let args = fixture_build_args(&root, "public", &[]);
+        let out = disk_output(&args);
+        let mut build = build_site(&args, &out, false).unwrap();
+        let hashed = |files: &BTreeMap<String, Vec<u8>>, name: &str| {
+            let manifest: BTreeMap<String, String> =
+                serde_json::from_slice(&files["assets-manifest.json"]).unwrap();
+            manifest[name].clone()
+        };
+
+        let files = output_files(&args.out_dir);
+        let css = hashed(&files, "app.css");
+        let logo = hashed(&files, "logo.svg");
+        assert!(!files.contains_key("assets/app.css"));
+        let page = String::from_utf8_lossy(&files["en/index.html"]).into_owned();
+        assert!(page.contains(&format!("assets/{css}\"")), "stylesheet not rewritten");
+        assert!(page.contains(&logo), "logo not rewritten");
+        assert!(!page.contains("assets/app.css"));
+        let headers = String::from_utf8_lossy(&files["_headers"]).into_owned();
+        assert!(headers.contains(&format!("/assets/{css}\n  Cache-Control: {}", assets::IMMUTABLE)));
+
+        // A new stylesheet is a new name, and every page moves to it.
+        fs::write(root.join("assets/app.css"), "body{color:red}").unwrap();
+        let changed = [root.join("assets/app.css")];
+        let rebuilt = rebuild_changed(&args, &mut build, &changed, &out, false).unwrap();
+        assert_eq!(rebuilt, Rebuilt::Site);
+        let files = output_files(&args.out_dir);
+        let restyled = hashed(&files, "app.css");
+        assert_ne!(restyled, css);
+        assert!(!files.contains_key(&format!("assets/{css}")));
+        let page = String::from_utf8_lossy(&files["en/index.html"]).into_owned();
+        assert!(page.contains(&format!("assets/{restyled}\"")));
+    }
+
+    #[test]
+    fn incremental_rebuild_matches_a_full_build() {
+        let root = TempDir::new("incremental", &[]);
+        let docs = root.join("docs");
+        let write = |rel: &str, content: &str| root.write(&format!("docs/{rel}"), content);
+        write("en/welcome.md", "# Welcome\n\nSee [errors](errors.md).\n\n@include: note.md\n");
+        write("en/errors.md", "# Errors\n\nOld text.\n");
+        write("en/note.md", "# Note\n\nFirst note.\n");
+        write("hi/welcome.md", "# स्वागत\n");
+        write("../assets/app.css", "body{}");
+        let args = |out: &str| fixture_build_args(&root, out, &[]);
+        let files = |out: &str| output_files(&root.join(out));
+
+        let mut build = build_site(&args("inc"), &disk_output(&args("inc")), true).unwrap();
+        // A full build wipes the output first; this file surviving shows the
+        // rebuild took the incremental path.
+        let sentinel = root.join("inc/sentinel");
+        fs::write(&sentinel, "").unwrap();
+
+        write("en/errors.md", "# Errors\n\nNew text.\n");
+        write("en/note.md", "# Note\n\nSecond note.\n");
+        write("../assets/app.css", "body{color:red}");
+        let changed = [
+            docs.join("en/errors.md"),
+            docs.join("en/note.md"),
+            root.join("assets/app.css"),
+        ];
+        rebuild_changed(&args("inc"), &mut build, &changed, &disk_output(&args("inc")), true).unwrap();
+        assert!(sentinel.exists());
+        fs::remove_file(&sentinel).unwrap();
+        build_site(&args("full"), &disk_output(&args("full")), true).unwrap();
+        assert_eq!(files("inc"), files("full"));
+        assert!(String::from_utf8_lossy(&files("inc")["en/index.html"]).contains("Second note."));
+        assert_eq!(files("inc")["assets/app.css"], b"body{color:red}");
+
+        // Assets alone render nothing; a stylesheet is swapped in place.
+        write("../assets/app.css", "body{color:blue}");
+        write("../assets/logo.svg", "<svg/>");
+        let out = disk_output(&args("inc"));
+        let restyled = [root.join("assets/app.css")];
+        assert_eq!(rebuild_changed(&args("inc"), &mut build, &restyled, &out, true).unwrap(), Rebuilt::Styles);
+        let added = [root.join("assets/logo.svg")];
+        assert_eq!(rebuild_changed(&args("inc"), &mut build, &added, &out, true).unwrap(), Rebuilt::Site);
+        fs::remove_file(root.join("assets/logo.svg")).unwrap();
+        rebuild_changed(&args("inc"), &mut build, &added, &out, true).unwrap();
+        build_site(&args("full"), &disk_output(&args("full")), true).unwrap();
+        assert_eq!(files("inc"), files("full"));
+
+        // A new title renames sidebar entries everywhere: full rebuild.
+        fs::write(&sentinel, "").unwrap();
+        write("en/errors.md", "# Error codes\n\nNew text.\n");
+        let changed = [docs.join("en/errors.md")];
+        rebuild_changed(&args("inc"), &mut build, &changed, &disk_output(&args("inc")), true).unwrap();
+        assert!(!sentinel.exists());
+        build_site(&args("full"), &disk_output(&args("full")), true).unwrap();
+        assert_eq!(files("inc"), files("full"));
+    }
+
     #[test]
     fn slugs_are_unique_within_a_page() {
-        let (_, toc) = markdown_to_html_with_toc("## Setup\n\ntext\n\n## Setup\n\nmore\n");
+        let (_, toc) = markdown_to_html_with_toc("## Setup\n\ntext\n\n## Setup\n\nmore\n", None);
         let ids: Vec<&str> = toc.iter().map(|item| item.id.as_str()).collect();
         assert_eq!(ids, vec!["setup", "setup-2"]);
     }
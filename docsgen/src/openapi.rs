@@ -0,0 +1,1843 @@
+//! `docsgen export openapi`: every `#### Request` card in the docs tree, with
+//! the `#### Response` cards after it, as one OpenAPI 3.1 document. The pages
+//! stay the source of truth; SDK generators and gateways read the export.
+//!
+//! `docsgen import openapi` goes the other way, for a service whose spec
+//! came first: it writes pages in the same grammar from the spec.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use pulldown_cmark::{Event, Parser as MdParser, Tag, TagEnd};
+use serde_json::{Map, Value, json};
+
+use crate::check::Diagnostic;
+use crate::samples::{self, RequestFields};
+use crate::{
+    ApiBlock, DEFAULT_API_BASE, OpenapiImportArgs, PageMeta, breadcrumb_for, build_api_blocks,
+    collect_site_meta, expand_includes, load_nav_groups, load_site_config, md_options, parse_nav,
+    slugify, split_frontmatter, split_sections,
+};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum Format {
+    Json,
+    Yaml,
+}
+
+/// Build the document from the pages of `lang` (the default language when
+/// `None`). Cards that cannot become an operation are reported and skipped.
+pub(crate) fn export(
+    docs_dir: &Path,
+    lang: Option<&str>,
+    drafts: bool,
+    api_version: &str,
+) -> Result<Value> {
+    if !docs_dir.exists() {
+        return Err(anyhow!("docs dir not found: {}", docs_dir.display()));
+    }
+
+    let site = collect_site_meta(docs_dir, drafts, "")?;
+    let code = lang.unwrap_or(&site.default_lang);
+    let lang = site
+        .langs
+        .iter()
+        .find(|l| l.code == code)
+        .ok_or_else(|| anyhow!("no language '{code}' in {}", docs_dir.display()))?;
+    let lang_dir = docs_dir.join(&lang.code);
+    let config = load_site_config(docs_dir, &lang.code);
+    let api_base = config.api_base.as_deref().unwrap_or(DEFAULT_API_BASE);
+    let nav_groups = load_nav_groups(&lang_dir, &lang.pages);
+
+    let mut doc = Document::default();
+    // A stand-in is another language's page; its endpoints are already there.
+    for page in lang
+        .pages
+        .iter()
+        .filter(|page| page.fallback_from.is_none())
+    {
+        let md_path = lang_dir.join(&page.source_rel);
+        let source = fs::read_to_string(&md_path)
+            .with_context(|| format!("failed to read {}", md_path.display()))?;
+        let (_, markdown) = split_frontmatter(&source)
+            .with_context(|| format!("invalid frontmatter in {}", md_path.display()))?;
+        let base_dir = md_path.parent().unwrap_or(docs_dir);
+        let expanded = expand_includes(markdown, base_dir)
+            .with_context(|| format!("failed to expand includes in {}", md_path.display()))?;
+        let (desc_md, _, json_md, _) = split_sections(&expanded);
+        let context = PageContext {
+            page,
+            description: page
+                .meta
+                .description
+                .clone()
+                .or_else(|| first_paragraph(&desc_md)),
+            tag: breadcrumb_for(&nav_groups, &page.url),
+        };
+        for diagnostic in doc.add_page(&context, &build_api_blocks(&json_md, api_base, &[])) {
+            eprintln!("{}", diagnostic.with_file(&md_path));
+        }
+    }
+
+    let mut info = Map::new();
+    info.insert(
+        "title".into(),
+        json!(config.title.as_deref().unwrap_or("API")),
+    );
+    if let Some(subtitle) = &config.subtitle {
+        info.insert("description".into(), json!(subtitle));
+    }
+    info.insert("version".into(), json!(api_version));
+
+    let mut root = Map::new();
+    root.insert("openapi".into(), json!("3.1.0"));
+    root.insert("info".into(), Value::Object(info));
+    root.insert(
+        "servers".into(),
+        json!([{ "url": api_base.trim_end_matches('/') }]),
+    );
+    if !doc.tags.is_empty() {
+        let tags: Vec<Value> = doc
+            .tags
+            .iter()
+            .map(|name| json!({ "name": name }))
+            .collect();
+        root.insert("tags".into(), Value::Array(tags));
+    }
+    root.insert("paths".into(), json!(doc.paths));
+    let mut components = Map::new();
+    if !doc.responses.is_empty() {
+        components.insert("responses".into(), json!(doc.responses));
+    }
+    if !doc.security_schemes.is_empty() {
+        components.insert("securitySchemes".into(), json!(doc.security_schemes));
+    }
+    if !components.is_empty() {
+        root.insert("components".into(), Value::Object(components));
+    }
+    Ok(Value::Object(root))
+}
+
+/// Write `doc` to `out`, or to stdout without one.
+pub(crate) fn write(doc: &Value, format: Format, out: Option<&Path>) -> Result<()> {
+    let text = match format {
+        Format::Json => serde_json::to_string_pretty(doc)? + "\n",
+        Format::Yaml => serde_yaml_ng::to_string(doc)?,
+    };
+    match out {
+        Some(path) => {
+            fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => Ok(std::io::stdout().write_all(text.as_bytes())?),
+    }
+}
+
+/// What an operation borrows from the page its cards are on.
+struct PageContext<'a> {
+    page: &'a PageMeta,
+    description: Option<String>,
+    /// The nav group the page is listed under.
+    tag: Option<String>,
+}
+
+/// A card that could not be exported, before the page's path is known.
+struct Skipped {
+    code: &'static str,
+    message: String,
+}
+
+impl Skipped {
+    fn with_file(&self, path: &Path) -> Diagnostic {
+        Diagnostic::warning(self.code, path, None, self.message.clone())
+    }
+}
+
+#[derive(Default)]
+struct Document {
+    /// path → method → operation.
+    paths: BTreeMap<String, BTreeMap<String, Map<String, Value>>>,
+    tags: BTreeSet<String>,
+    security_schemes: BTreeMap<String, Value>,
+    /// Response cards that follow no request, by component name.
+    responses: BTreeMap<String, Value>,
+    operation_ids: BTreeSet<String>,
+}
+
+impl Document {
+    /// Each request card opens an operation and the response cards after it,
+    /// up to the next request, are its responses. An endpoint documented on
+    /// several pages is described by the first and collects the response
+    /// statuses the others add.
+    fn add_page(&mut self, context: &PageContext, blocks: &[ApiBlock]) -> Vec<Skipped> {
+        let mut skipped = Vec::new();
+        let mut current: Option<(String, String)> = None;
+        for block in blocks {
+            match block.kind.as_str() {
+                "request" => {
+                    current = None;
+                    let Some(path) = &block.path else {
+                        skipped.push(Skipped {
+                            code: "openapi-no-path",
+                            message: format!(
+                                "request card '{}' has no path; not exported",
+                                block.label
+                            ),
+                        });
+                        continue;
+                    };
+                    let method = block.method.as_deref().unwrap_or("GET").to_lowercase();
+                    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+                    let path = path_template(path);
+                    let exists = self
+                        .paths
+                        .get(&path)
+                        .is_some_and(|methods| methods.contains_key(&method));
+                    if !exists {
+                        let operation = self.operation(context, block, &method, &path, query);
+                        self.paths
+                            .entry(path.clone())
+                            .or_default()
+                            .insert(method.clone(), operation);
+                    }
+                    current = Some((path, method));
+                }
+                "response" => {
+                    // An error catalogue lists responses on their own; they
+                    // become reusable `components.responses`.
+                    let Some((path, method)) = &current else {
+                        let name = component_name(block);
+                        self.responses
+                            .entry(name)
+                            .or_insert_with(|| response(block));
+                        continue;
+                    };
+                    let status = block.status.clone().unwrap_or_else(|| "default".into());
+                    let operation = self
+                        .paths
+                        .get_mut(path)
+                        .and_then(|methods| methods.get_mut(method))
+                        .expect("operation added with its request");
+                    let responses = operation
+                        .entry("responses")
+                        .or_insert_with(|| Value::Object(Map::new()))
+                        .as_object_mut()
+                        .expect("responses is an object");
+                    responses.entry(status).or_insert_with(|| response(block));
+                }
+                _ => {}
+            }
+        }
+        skipped
+    }
+
+    fn operation(
+        &mut self,
+        context: &PageContext,
+        block: &ApiBlock,
+        method: &str,
+        path: &str,
+        query: &str,
+    ) -> Map<String, Value> {
+        let mut op = Map::new();
+        if let Some(tag) = &context.tag {
+            self.tags.insert(tag.clone());
+            op.insert("tags".into(), json!([tag]));
+        }
+        // A card titled on its own ("Request POST /v1/x — Rotate key") names
+        // the operation; otherwise the page title does.
+        let summary = match block.label.as_str() {
+            "Request" => context.page.title.as_str(),
+            label => label,
+        };
+        op.insert("summary".into(), json!(summary));
+        let description: Vec<&str> = [context.description.as_deref(), block.note.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !description.is_empty() {
+            op.insert("description".into(), json!(description.join("\n\n")));
+        }
+        op.insert("operationId".into(), json!(self.operation_id(method, path)));
+
+        // A card that is only a body has no envelope to read.
+        let request = RequestFields::parse(&block.raw).unwrap_or_else(|| RequestFields {
+            body: (!block.raw.trim().is_empty()).then(|| samples::Body::Raw(block.raw.clone())),
+            ..RequestFields::default()
+        });
+        let mut parameters: Vec<Value> = path_params(path)
+            .into_iter()
+            .map(|name| {
+                let mut param = json!({ "name": name, "in": "path", "required": true });
+                match request.path_params.iter().find(|(key, _)| key == name) {
+                    Some((_, value)) => {
+                        param["schema"] = infer_schema(value);
+                        param["example"] = value.clone();
+                    }
+                    None => param["schema"] = json!({ "type": "string" }),
+                }
+                param
+            })
+            .collect();
+        for (name, value) in
+            query_pairs(query).chain(request.query.iter().map(|(k, v)| (k.as_str(), v.clone())))
+        {
+            parameters.push(json!({
+                "name": name,
+                "in": "query",
+                "schema": infer_schema(&value),
+                "example": value,
+            }));
+        }
+        let mut content_type = "application/json".to_string();
+        let mut security = Vec::new();
+        for (name, value) in &request.headers {
+            match name.to_ascii_lowercase().as_str() {
+                "content-type" => content_type = value.clone(),
+                "accept" => {}
+                "authorization" => {
+                    let scheme = value.split_whitespace().next().unwrap_or_default();
+                    let (key, definition) = match scheme.to_ascii_lowercase().as_str() {
+                        "bearer" => ("bearerAuth", json!({ "type": "http", "scheme": "bearer" })),
+                        "basic" => ("basicAuth", json!({ "type": "http", "scheme": "basic" })),
+                        _ => (
+                            "authorizationHeader",
+                            json!({ "type": "apiKey", "in": "header", "name": "Authorization" }),
+                        ),
+                    };
+                    self.security_schemes.insert(key.to_string(), definition);
+                    security.push(json!({ key: [] }));
+                }
+                _ => parameters.push(json!({
+                    "name": name,
+                    "in": "header",
+                    "required": true,
+                    "schema": { "type": "string" },
+                    "example": value,
+                })),
+            }
+        }
+        if !parameters.is_empty() {
+            op.insert("parameters".into(), Value::Array(parameters));
+        }
+        if let Some(body) = &request.body {
+            let (content_type, media) = match body {
+                samples::Body::Raw(text) => {
+                    let body = match serde_json::from_str(text) {
+                        Ok(value) => Body::Json(value),
+                        Err(_) => Body::Text(text.clone()),
+                    };
+                    (content_type, media_type(&body))
+                }
+                samples::Body::Form(fields) => (
+                    "application/x-www-form-urlencoded".to_string(),
+                    form_media_type(fields, false),
+                ),
+                samples::Body::Multipart(fields) => (
+                    "multipart/form-data".to_string(),
+                    form_media_type(fields, true),
+                ),
+            };
+            op.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { content_type: media },
+                }),
+            );
+        }
+        if !security.is_empty() {
+            op.insert("security".into(), Value::Array(security));
+        }
+        op
+    }
+
+    /// `post /v1/resources/{id}` → `postV1ResourcesId`, made unique with a
+    /// numeric suffix.
+    fn operation_id(&mut self, method: &str, path: &str) -> String {
+        let mut id = method.to_string();
+        for word in path.split(|c: char| !c.is_ascii_alphanumeric()) {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                id.push(first.to_ascii_uppercase());
+                id.extend(chars);
+            }
+        }
+        let mut unique = id.clone();
+        let mut n = 2;
+        while !self.operation_ids.insert(unique.clone()) {
+            unique = format!("{id}{n}");
+            n += 1;
+        }
+        unique
+    }
+}
+
+enum Body {
+    Json(Value),
+    Text(String),
+}
+
+fn media_type(body: &Body) -> Value {
+    match body {
+        Body::Json(value) => json!({ "schema": infer_schema(value), "example": value }),
+        Body::Text(text) => json!({ "schema": { "type": "string" }, "example": text }),
+    }
+}
+
+/// A form or multipart body as an object schema with one property per
+/// field; a field given more than once is an array. Multipart `@path` values
+/// are files, which the example leaves out.
+fn form_media_type(fields: &[(String, String)], multipart: bool) -> Value {
+    let mut properties = Map::new();
+    let mut example = Map::new();
+    for (name, value) in fields {
+        let file = multipart && value.starts_with('@');
+        let schema = if file {
+            json!({ "type": "string", "format": "binary" })
+        } else {
+            infer_schema(&json!(value))
+        };
+        match properties.get_mut(name) {
+            Some(existing) if existing["type"] != "array" => {
+                *existing = json!({ "type": "array", "items": existing.take() });
+            }
+            Some(_) => {}
+            None => {
+                properties.insert(name.clone(), schema);
+            }
+        }
+        if file {
+            continue;
+        }
+        match example.get_mut(name) {
+            Some(Value::Array(items)) => items.push(json!(value)),
+            Some(first) => *first = json!([first.take(), value]),
+            None => {
+                example.insert(name.clone(), json!(value));
+            }
+        }
+    }
+    json!({
+        "schema": { "type": "object", "properties": properties },
+        "example": example,
+    })
+}
+
+/// A response card becomes `{ description, content }`. Its sample is the
+/// body, or `{ "status", "headers", "body" }` around one.
+fn response(block: &ApiBlock) -> Value {
+    let description = match (block.label.as_str(), &block.note) {
+        ("Response" | "Error", None) => block
+            .status
+            .as_deref()
+            .and_then(reason_phrase)
+            .unwrap_or(&block.label)
+            .to_string(),
+        (label, None) => label.to_string(),
+        (label, Some(note)) => format!("{label}\n\n{note}"),
+    };
+    let mut out = Map::new();
+    out.insert("description".into(), json!(description));
+
+    let raw = block.raw.trim();
+    if raw.is_empty() {
+        return Value::Object(out);
+    }
+    let body = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(fields))
+            if fields.contains_key("body")
+                && fields
+                    .get("status")
+                    .is_some_and(|s| s.is_number() || s.is_string()) =>
+        {
+            if let Some(headers) = fields.get("headers").and_then(Value::as_object) {
+                let headers: Map<String, Value> = headers
+                    .iter()
+                    .map(|(name, value)| {
+                        (
+                            name.clone(),
+                            json!({ "schema": infer_schema(value), "example": value }),
+                        )
+                    })
+                    .collect();
+                out.insert("headers".into(), Value::Object(headers));
+            }
+            Body::Json(fields["body"].clone())
+        }
+        Ok(value) => Body::Json(value),
+        Err(_) => Body::Text(raw.to_string()),
+    };
+    let content_type = match body {
+        Body::Json(_) => "application/json",
+        Body::Text(_) => "text/plain",
+    };
+    out.insert("content".into(), json!({ content_type: media_type(&body) }));
+    Value::Object(out)
+}
+
+fn reason_phrase(status: &str) -> Option<&'static str> {
+    Some(match status {
+        "200" => "OK",
+        "201" => "Created",
+        "202" => "Accepted",
+        "204" => "No Content",
+        "301" => "Moved Permanently",
+        "304" => "Not Modified",
+        "400" => "Bad Request",
+        "401" => "Unauthorized",
+        "403" => "Forbidden",
+        "404" => "Not Found",
+        "409" => "Conflict",
+        "422" => "Unprocessable Content",
+        "429" => "Too Many Requests",
+        "500" => "Internal Server Error",
+        "503" => "Service Unavailable",
+        _ => return None,
+    })
+}
+
+/// `/v1/resources/:id` → `/v1/resources/{id}`.
+fn path_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) if !name.is_empty() => format!("{{{name}}}"),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn path_params(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .collect()
+}
+
+fn query_pairs(query: &str) -> impl Iterator<Item = (&str, Value)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (name, json!(value))
+        })
+}
+
+/// The first paragraph of a page's prose, as plain text. Paragraphs inside
+/// quotes and lists (notes, callouts) are passed over.
+fn first_paragraph(md: &str) -> Option<String> {
+    let mut nested = 0;
+    let mut text: Option<String> = None;
+    for event in MdParser::new_ext(md, md_options()) {
+        match event {
+            Event::Start(Tag::BlockQuote | Tag::List(_)) => nested += 1,
+            Event::End(TagEnd::BlockQuote | TagEnd::List(_)) => nested -= 1,
+            Event::Start(Tag::Paragraph) if nested == 0 => text = Some(String::new()),
+            Event::End(TagEnd::Paragraph) => {
+                if let Some(paragraph) = text.take().filter(|p| !p.trim().is_empty()) {
+                    return Some(paragraph.trim().to_string());
+                }
+            }
+            Event::Text(chunk) | Event::Code(chunk) => {
+                if let Some(paragraph) = &mut text {
+                    paragraph.push_str(&chunk);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(paragraph) = &mut text {
+                    paragraph.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `Response 429 — Rate limited` → `RateLimited`; `Response 404` →
+/// `NotFound`.
+fn component_name(block: &ApiBlock) -> String {
+    let label = match block.label.as_str() {
+        "Response" | "Error" => block.status.as_deref().and_then(reason_phrase).map_or_else(
+            || {
+                format!(
+                    "{}{}",
+                    block.label,
+                    block.status.as_deref().unwrap_or_default()
+                )
+            },
+            str::to_string,
+        ),
+        label => label.to_string(),
+    };
+    label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// A JSON Schema the sample validates against: every key it has is
+/// required, and array items are the union of the elements' shapes.
+fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "type": "null" }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::String(s) if is_date_time(s) => json!({ "type": "string", "format": "date-time" }),
+        Value::String(_) => json!({ "type": "string" }),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(infer_schema)
+                .reduce(merge_schemas)
+                .unwrap_or_else(|| json!({}));
+            json!({ "type": "array", "items": items })
+        }
+        Value::Object(fields) => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(key, value)| (key.clone(), infer_schema(value)))
+                .collect();
+            let required: Vec<&String> = fields.keys().collect();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+    }
+}
+
+/// One schema for two samples of the same thing. Objects keep every
+/// property either has and require only those both have.
+fn merge_schemas(a: Value, b: Value) -> Value {
+    if a == b {
+        return a;
+    }
+    let kind = |schema: &Value| {
+        schema
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    match (kind(&a).as_deref(), kind(&b).as_deref()) {
+        (Some("object"), Some("object")) => {
+            let mut properties = a["properties"].as_object().cloned().unwrap_or_default();
+            for (key, schema) in b["properties"].as_object().into_iter().flatten() {
+                let merged = match properties.remove(key) {
+                    Some(existing) => merge_schemas(existing, schema.clone()),
+                    None => schema.clone(),
+                };
+                properties.insert(key.clone(), merged);
+            }
+            let required_in = |schema: &Value| -> BTreeSet<String> {
+                schema["required"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|key| key.as_str().map(str::to_string))
+                    .collect()
+            };
+            let required: Vec<String> = required_in(&a)
+                .intersection(&required_in(&b))
+                .cloned()
+                .collect();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+        (Some("integer" | "number"), Some("integer" | "number")) => json!({ "type": "number" }),
+        (Some("array"), Some("array")) => {
+            let items = merge_schemas(a["items"].clone(), b["items"].clone());
+            json!({ "type": "array", "items": items })
+        }
+        _ => {
+            let mut any_of = Vec::new();
+            for schema in [a, b] {
+                match schema.get("anyOf").and_then(Value::as_array) {
+                    Some(options) => any_of.extend(options.iter().cloned()),
+                    None => any_of.push(schema),
+                }
+            }
+            any_of.dedup();
+            json!({ "anyOf": any_of })
+        }
+    }
+}
+
+/// `2024-01-01T00:00:00Z` and the like.
+fn is_date_time(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    bytes.len() >= 20
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && matches!(bytes[10], b'T' | b't')
+        && bytes[13] == b':'
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+}
+
+// ─── Import ──────────────────────────────────────────────────────────────────
+
+/// How `import openapi` splits a spec into pages.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum PageSplit {
+    /// One page per tag, holding every operation tagged with it.
+    Tag,
+    /// One page per operation.
+    Operation,
+}
+
+/// What an import wrote, for the summary line.
+#[derive(Debug, Default)]
+pub(crate) struct ImportReport {
+    pub(crate) written: Vec<PathBuf>,
+    pub(crate) updated: Vec<PathBuf>,
+    pub(crate) unchanged: usize,
+    pub(crate) nav_added: usize,
+}
+
+/// Path item keys that are operations, in the order pages list them.
+const OPERATION_KEYS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// Write one page per tag (or operation) of the spec under `--out`, and list
+/// them in `nav.md`. Everything generated sits between `docsgen:begin` /
+/// `docsgen:end` markers; `--update` rewrites only those, so prose added
+/// around them survives a re-import.
+pub(crate) fn import(args: &OpenapiImportArgs) -> Result<ImportReport> {
+    let text = fs::read_to_string(&args.spec)
+        .with_context(|| format!("failed to read {}", args.spec.display()))?;
+    // JSON is YAML too, so one parser reads either.
+    let spec: Value = serde_yaml_ng::from_str(&text)
+        .with_context(|| format!("failed to parse {}", args.spec.display()))?;
+    let version = spec
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !version.starts_with('3') {
+        return Err(anyhow!(
+            "{} is not an OpenAPI 3 document",
+            args.spec.display()
+        ));
+    }
+
+    let lang_dir = args.docs_dir.join(&args.lang);
+    let out_dir = args.out.clone().unwrap_or_else(|| lang_dir.join("api"));
+    fs::create_dir_all(&out_dir)
+        .with_context(|| format!("failed to create {}", out_dir.display()))?;
+    let nav_prefix = fs::canonicalize(&out_dir)?
+        .strip_prefix(fs::canonicalize(&lang_dir)?)
+        .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+        .map_err(|_| {
+            anyhow!(
+                "--out {} is not inside {}",
+                out_dir.display(),
+                lang_dir.display()
+            )
+        })?;
+
+    let pages = plan_pages(&spec, args.by);
+    let existing: Vec<String> = pages
+        .iter()
+        .map(|page| out_dir.join(&page.file))
+        .filter(|path| path.exists())
+        .map(|path| format!("  {}", path.display()))
+        .collect();
+    if !args.update && !existing.is_empty() {
+        return Err(anyhow!(
+            "pages already exist; pass --update to refresh their generated regions:\n{}",
+            existing.join("\n")
+        ));
+    }
+
+    let mut report = ImportReport::default();
+    for page in &pages {
+        let path = out_dir.join(&page.file);
+        let fresh = render_page(&spec, page, args.by);
+        if !path.exists() {
+            fs::write(&path, fresh)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            report.written.push(path);
+            continue;
+        }
+        let current = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let (merged, missing) = update_regions(&current, &fresh);
+        for name in missing {
+            let message = format!("no `{name}` region to update; left as it is");
+            eprintln!(
+                "{}",
+                Diagnostic::warning("openapi-region-missing", &path, None, message)
+            );
+        }
+        if merged == current {
+            report.unchanged += 1;
+        } else {
+            fs::write(&path, merged)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            report.updated.push(path);
+        }
+    }
+
+    let group = args.group.clone().unwrap_or_else(|| {
+        spec.pointer("/info/title")
+            .and_then(Value::as_str)
+            .unwrap_or("API reference")
+            .to_string()
+    });
+    let targets: Vec<String> = pages
+        .iter()
+        .map(|page| match nav_prefix.as_str() {
+            "" => page.file.clone(),
+            prefix => format!("{prefix}/{}", page.file),
+        })
+        .collect();
+    report.nav_added = append_nav(&lang_dir.join("nav.md"), &group, &targets)?;
+    Ok(report)
+}
+
+struct Operation<'a> {
+    method: &'static str,
+    path: &'a str,
+    op: &'a Value,
+    /// `parameters` on the path item apply to each of its operations.
+    shared_parameters: &'a [Value],
+}
+
+struct PlannedPage<'a> {
+    file: String,
+    title: String,
+    description: Option<String>,
+    operations: Vec<Operation<'a>>,
+}
+
+fn plan_pages(spec: &Value, by: PageSplit) -> Vec<PlannedPage<'_>> {
+    let mut operations = Vec::new();
+    for (path, item) in spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let shared_parameters = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .map_or(&[][..], Vec::as_slice);
+        for method in OPERATION_KEYS {
+            if let Some(op) = item.get(method) {
+                operations.push(Operation {
+                    method,
+                    path,
+                    op,
+                    shared_parameters,
+                });
+            }
+        }
+    }
+
+    let mut pages: Vec<PlannedPage> = Vec::new();
+    match by {
+        PageSplit::Tag => {
+            let declared = spec.get("tags").and_then(Value::as_array);
+            let tag_description = |name: &str| {
+                declared
+                    .into_iter()
+                    .flatten()
+                    .find(|tag| tag.get("name").and_then(Value::as_str) == Some(name))
+                    .and_then(|tag| tag.get("description").and_then(Value::as_str))
+                    .map(str::to_string)
+            };
+            // Declared tags keep the spec's order, even before their first
+            // operation; the rest follow as they first appear.
+            let mut names: Vec<String> = declared
+                .into_iter()
+                .flatten()
+                .filter_map(|tag| tag.get("name").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect();
+            let mut by_tag: Vec<Vec<Operation>> = names.iter().map(|_| Vec::new()).collect();
+            for operation in operations {
+                // An operation is documented once, on its first tag's page.
+                let tag = operation
+                    .op
+                    .pointer("/tags/0")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Other");
+                let idx = match names.iter().position(|name| name == tag) {
+                    Some(idx) => idx,
+                    None => {
+                        names.push(tag.to_string());
+                        by_tag.push(Vec::new());
+                        names.len() - 1
+                    }
+                };
+                by_tag[idx].push(operation);
+            }
+            for (name, operations) in names.into_iter().zip(by_tag) {
+                if operations.is_empty() {
+                    continue;
+                }
+                pages.push(PlannedPage {
+                    file: slugify(&name),
+                    description: tag_description(&name),
+                    title: name,
+                    operations,
+                });
+            }
+        }
+        PageSplit::Operation => {
+            for operation in operations {
+                let op = operation.op;
+                let title = op.get("summary").and_then(Value::as_str).map_or_else(
+                    || format!("{} {}", operation.method.to_uppercase(), operation.path),
+                    str::to_string,
+                );
+                let file = match op.get("operationId").and_then(Value::as_str) {
+                    Some(id) => slugify(&split_camel_case(id)),
+                    None => slugify(&format!("{} {}", operation.method, operation.path)),
+                };
+                pages.push(PlannedPage {
+                    file,
+                    title,
+                    description: None,
+                    operations: vec![operation],
+                });
+            }
+        }
+    }
+
+    let mut taken = BTreeSet::new();
+    for page in &mut pages {
+        let stem = match page.file.as_str() {
+            "" | "nav" | "site" | "template" | "welcome" => format!("api-{}", page.file),
+            stem => stem.to_string(),
+        };
+        let mut file = format!("{stem}.md");
+        let mut n = 2;
+        while !taken.insert(file.clone()) {
+            file = format!("{stem}-{n}.md");
+            n += 1;
+        }
+        page.file = file;
+    }
+    pages
+}
+
+/// `listPets` → `list Pets`, so it slugifies to `list-pets`.
+fn split_camel_case(id: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for ch in id.chars() {
+        if ch.is_uppercase() && prev_lower {
+            out.push(' ');
+        }
+        prev_lower = ch.is_lowercase() || ch.is_ascii_digit();
+        out.push(ch);
+    }
+    out
+}
+
+fn region(name: &str, body: &str) -> String {
+    format!(
+        "<!-- docsgen:begin {name} -->\n\n{}\n\n<!-- docsgen:end {name} -->",
+        body.trim()
+    )
+}
+
+fn render_page(spec: &Value, page: &PlannedPage, by: PageSplit) -> String {
+    let mut description = String::new();
+    if let Some(text) = &page.description {
+        description.push_str(text.trim());
+        description.push_str("\n\n");
+    }
+    let mut cards = String::new();
+    for operation in &page.operations {
+        let op = operation.op;
+        let method = operation.method.to_uppercase();
+        let summary = op.get("summary").and_then(Value::as_str);
+        if by == PageSplit::Tag {
+            let heading =
+                summary.map_or_else(|| format!("{method} {}", operation.path), str::to_string);
+            description.push_str(&format!("### {heading}\n\n"));
+        }
+        description.push_str(&format!("`{method} {}`\n\n", operation.path));
+        if let Some(text) = op.get("description").and_then(Value::as_str) {
+            description.push_str(text.trim());
+            description.push_str("\n\n");
+        }
+        if op.get("deprecated").and_then(Value::as_bool) == Some(true) {
+            description.push_str("**Deprecated.**\n\n");
+        }
+        description.push_str(&parameter_table(spec, operation));
+        description.push_str(&body_table(spec, op));
+
+        cards.push_str(&request_card(spec, operation, summary));
+        for (status, response) in op
+            .get("responses")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            cards.push_str(&response_card(spec, status, resolve(spec, response)));
+        }
+    }
+
+    format!(
+        "# {}\n\n## Description\n\n{}\n\n## Architecture\n\n### JSON\n\n{}\n",
+        page.title,
+        region("description", &description),
+        region("cards", &cards),
+    )
+}
+
+fn parameters<'a>(spec: &'a Value, operation: &Operation<'a>) -> Vec<&'a Value> {
+    let own = operation
+        .op
+        .get("parameters")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+    let mut params: Vec<&Value> = own.iter().map(|param| resolve(spec, param)).collect();
+    // An operation's own parameter overrides a shared one of the same name.
+    for shared in operation.shared_parameters {
+        let shared = resolve(spec, shared);
+        if !params.iter().any(|param| {
+            param.get("name") == shared.get("name") && param.get("in") == shared.get("in")
+        }) {
+            params.push(shared);
+        }
+    }
+    params
+}
+
+fn parameter_table(spec: &Value, operation: &Operation) -> String {
+    let params = parameters(spec, operation);
+    if params.is_empty() {
+        return String::new();
+    }
+    let mut table = String::from(
+        "**Parameters**\n\n| Name | In | Type | Required | Description |\n| --- | --- | --- | --- | --- |\n",
+    );
+    for param in params {
+        let field = |key: &str| param.get(key).and_then(Value::as_str).unwrap_or_default();
+        let required = param.get("required").and_then(Value::as_bool) == Some(true);
+        table.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            field("name"),
+            field("in"),
+            param
+                .get("schema")
+                .map_or_else(String::new, |schema| schema_type(spec, schema)),
+            if required { "yes" } else { "no" },
+            table_cell(field("description")),
+        ));
+    }
+    table.push('\n');
+    table
+}
+
+/// The top-level fields of a JSON request body.
+fn body_table(spec: &Value, op: &Value) -> String {
+    let Some((_, media)) = request_media(spec, op) else {
+        return String::new();
+    };
+    let Some(schema) = media.get("schema").map(|schema| resolve(spec, schema)) else {
+        return String::new();
+    };
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return String::new();
+    };
+    let required: BTreeSet<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut table = String::from(
+        "**Request body**\n\n| Field | Type | Required | Description |\n| --- | --- | --- | --- |\n",
+    );
+    for (name, property) in properties {
+        let property = resolve(spec, property);
+        table.push_str(&format!(
+            "| `{name}` | {} | {} | {} |\n",
+            schema_type(spec, property),
+            if required.contains(name.as_str()) {
+                "yes"
+            } else {
+                "no"
+            },
+            table_cell(
+                property
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+    table.push('\n');
+    table
+}
+
+fn table_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+/// `string (date-time)`, `array of Pet`, `integer`.
+fn schema_type(spec: &Value, schema: &Value) -> String {
+    if let Some(name) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.rsplit('/').next())
+    {
+        return name.to_string();
+    }
+    let schema = resolve(spec, schema);
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.clone(),
+        // 3.1 writes a nullable string as `type: [string, "null"]`.
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|kind| *kind != "null")
+            .collect::<Vec<_>>()
+            .join(" or "),
+        _ if schema.get("properties").is_some() => "object".to_string(),
+        _ => return String::new(),
+    };
+    match (kind.as_str(), schema.get("format").and_then(Value::as_str)) {
+        ("array", _) => match schema.get("items") {
+            Some(items) => format!("array of {}", schema_type(spec, items)),
+            None => kind,
+        },
+        (_, Some(format)) => format!("{kind} ({format})"),
+        _ => kind,
+    }
+}
+
+/// The media type a request body is documented with: JSON when offered.
+fn request_media<'a>(spec: &'a Value, op: &'a Value) -> Option<(&'a str, &'a Value)> {
+    let body = resolve(spec, op.get("requestBody")?);
+    let content = body.get("content")?.as_object()?;
+    content
+        .iter()
+        .find(|(kind, _)| kind.contains("json"))
+        .or_else(|| content.iter().next())
+        .map(|(kind, media)| (kind.as_str(), media))
+}
+
+fn request_card(spec: &Value, operation: &Operation, summary: Option<&str>) -> String {
+    let mut path_params = Map::new();
+    let mut query = Map::new();
+    let mut headers = Map::new();
+    for param in parameters(spec, operation) {
+        let name = param
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let value = || param_example(spec, param);
+        match param.get("in").and_then(Value::as_str) {
+            Some("path") => {
+                path_params.insert(name.to_string(), value());
+            }
+            Some("query")
+                if param.get("required").and_then(Value::as_bool) == Some(true)
+                    || param.get("example").is_some() =>
+            {
+                query.insert(name.to_string(), value());
+            }
+            Some("header") => {
+                headers.insert(name.to_string(), value());
+            }
+            _ => {}
+        }
+    }
+    let security = operation
+        .op
+        .get("security")
+        .or_else(|| spec.get("security"))
+        .and_then(Value::as_array);
+    if let Some(scheme) = security
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .flat_map(|requirement| requirement.keys())
+        .next()
+        .and_then(|name| spec.pointer(&format!("/components/securitySchemes/{name}")))
+        .map(|scheme| resolve(spec, scheme))
+    {
+        let field = |key: &str| scheme.get(key).and_then(Value::as_str).unwrap_or_default();
+        match (field("type"), field("scheme").to_ascii_lowercase().as_str()) {
+            ("http", "bearer") => {
+                headers.insert("Authorization".into(), json!("Bearer <token>"));
+            }
+            ("http", "basic") => {
+                headers.insert("Authorization".into(), json!("Basic <credentials>"));
+            }
+            ("apiKey", _) if field("in") == "header" => {
+                headers.insert(field("name").to_string(), json!("<api-key>"));
+            }
+            ("oauth2" | "openIdConnect", _) => {
+                headers.insert("Authorization".into(), json!("Bearer <token>"));
+            }
+            _ => {}
+        }
+    }
+    let media = request_media(spec, operation.op);
+    // Form and multipart bodies become the card's `form` / `multipart`
+    // fields, whose samples set the content type (and boundary) themselves.
+    let form = media.and_then(|(kind, media)| match kind {
+        "application/x-www-form-urlencoded" => Some(("form", form_fields(spec, media, false)?)),
+        kind if kind.starts_with("multipart/form-data") => {
+            Some(("multipart", form_fields(spec, media, true)?))
+        }
+        _ => None,
+    });
+    if let (Some((kind, _)), None) = (media, &form) {
+        headers.insert("Content-Type".into(), json!(kind));
+    }
+
+    let mut sample = Map::new();
+    sample.insert("method".into(), json!(operation.method.to_uppercase()));
+    sample.insert("path".into(), json!(operation.path));
+    if !path_params.is_empty() {
+        sample.insert("path_params".into(), Value::Object(path_params));
+    }
+    if !query.is_empty() {
+        sample.insert("query".into(), Value::Object(query));
+    }
+    if !headers.is_empty() {
+        sample.insert("headers".into(), Value::Object(headers));
+    }
+    match (form, media) {
+        (Some((field, fields)), _) => {
+            sample.insert(field.into(), Value::Object(fields));
+        }
+        (None, Some((_, media))) => {
+            sample.insert("body".into(), media_example(spec, media));
+        }
+        (None, None) => {}
+    }
+
+    let label = summary.map(|s| format!(" — {s}")).unwrap_or_default();
+    format!(
+        "#### Request {} {}{label}\n\n```json\n{}\n```\n\n",
+        operation.method.to_uppercase(),
+        operation.path,
+        serde_json::to_string_pretty(&sample).unwrap_or_default(),
+    )
+}
+
+/// A form or multipart body's fields: its example, with each binary
+/// property of a multipart schema as an `@<file>` upload. `None` when the
+/// example is not an object.
+fn form_fields(spec: &Value, media: &Value, multipart: bool) -> Option<Map<String, Value>> {
+    let Value::Object(mut fields) = media_example(spec, media) else {
+        return None;
+    };
+    if multipart {
+        let schema = media.get("schema").map(|schema| resolve(spec, schema));
+        let properties = schema
+            .and_then(|schema| schema.get("properties"))
+            .and_then(Value::as_object);
+        for (name, property) in properties.into_iter().flatten() {
+            if resolve(spec, property)
+                .get("format")
+                .and_then(Value::as_str)
+                == Some("binary")
+            {
+                fields.insert(name.clone(), json!("@<file>"));
+            }
+        }
+    }
+    Some(fields)
+}
+
+fn response_card(spec: &Value, status: &str, response: &Value) -> String {
+    let status = match status {
+        "default" => String::new(),
+        status => format!(" {status}"),
+    };
+    let description = response
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .trim();
+    // A one-line description is the card's title; a longer one its note.
+    let (label, note) = match description.lines().count() {
+        0 => (String::new(), String::new()),
+        1 if description.len() <= 80 => (
+            format!(" — {}", description.trim_end_matches('.')),
+            String::new(),
+        ),
+        _ => (String::new(), format!("{description}\n\n")),
+    };
+    let content = response.get("content").and_then(Value::as_object);
+    let media = content.and_then(|content| {
+        content
+            .iter()
+            .find(|(kind, _)| kind.contains("json"))
+            .or_else(|| content.iter().next())
+    });
+    let (lang, code) = match media {
+        Some((kind, media)) if kind.contains("json") => (
+            "json",
+            serde_json::to_string_pretty(&media_example(spec, media)).unwrap_or_default(),
+        ),
+        Some((_, media)) => match media_example(spec, media) {
+            Value::String(text) => ("text", text),
+            other => ("text", other.to_string()),
+        },
+        None => ("text", String::new()),
+    };
+    let code = match code.as_str() {
+        "" => String::new(),
+        code => format!("{code}\n"),
+    };
+    format!("#### Response{status}{label}\n\n{note}```{lang}\n{code}```\n\n")
+}
+
+/// The spec's own example for a media type, else one built from its schema.
+fn media_example(spec: &Value, media: &Value) -> Value {
+    if let Some(example) = media.get("example") {
+        return example.clone();
+    }
+    if let Some((_, example)) = media
+        .get("examples")
+        .and_then(Value::as_object)
+        .and_then(|examples| examples.iter().next())
+        && let Some(value) = resolve(spec, example).get("value")
+    {
+        return value.clone();
+    }
+    media
+        .get("schema")
+        .map_or(Value::Null, |schema| example_from_schema(spec, schema, 0))
+}
+
+fn param_example(spec: &Value, param: &Value) -> Value {
+    if let Some(example) = param.get("example") {
+        return example.clone();
+    }
+    param
+        .get("schema")
+        .map_or_else(|| json!(""), |schema| example_from_schema(spec, schema, 0))
+}
+
+/// A plausible value for `schema`: its own example, default or first enum
+/// value where it has one, otherwise a placeholder of the right type.
+fn example_from_schema(spec: &Value, schema: &Value, depth: usize) -> Value {
+    // Recursive schemas (a tree node holding its children) stop here.
+    if depth > 8 {
+        return Value::Null;
+    }
+    let schema = resolve(spec, schema);
+    for key in ["example", "default", "const"] {
+        if let Some(value) = schema.get(key) {
+            return value.clone();
+        }
+    }
+    if let Some(value) = schema
+        .pointer("/examples/0")
+        .or_else(|| schema.pointer("/enum/0"))
+    {
+        return value.clone();
+    }
+    if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for part in parts {
+            if let Value::Object(fields) = example_from_schema(spec, part, depth + 1) {
+                merged.extend(fields);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = schema
+        .pointer("/oneOf/0")
+        .or_else(|| schema.pointer("/anyOf/0"))
+    {
+        return example_from_schema(spec, first, depth + 1);
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null")
+            .unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ if schema.get("items").is_some() => "array",
+        _ => return Value::Null,
+    };
+    match kind {
+        "object" => Value::Object(
+            schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| {
+                    (name.clone(), example_from_schema(spec, property, depth + 1))
+                })
+                .collect(),
+        ),
+        "array" => match schema.get("items") {
+            Some(items) => json!([example_from_schema(spec, items, depth + 1)]),
+            None => json!([]),
+        },
+        "integer" => json!(0),
+        "number" => json!(0.0),
+        "boolean" => json!(true),
+        "string" => json!(match schema.get("format").and_then(Value::as_str) {
+            Some("date-time") => "2024-01-01T00:00:00Z",
+            Some("date") => "2024-01-01",
+            Some("email") => "user@example.com",
+            Some("uuid") => "00000000-0000-0000-0000-000000000000",
+            Some("uri" | "url") => "https://example.com",
+            _ => "string",
+        }),
+        _ => Value::Null,
+    }
+}
+
+/// Follow a local `$ref` (`#/components/schemas/Pet`). Anything else,
+/// including a ref to another file, is returned as it is.
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    // A chain of refs, not a cycle, is what a spec can legitimately hold.
+    for _ in 0..16 {
+        let Some(target) = value
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| spec.pointer(pointer))
+        else {
+            break;
+        };
+        value = target;
+    }
+    value
+}
+
+/// Replace each `docsgen:begin name` … `docsgen:end name` region of
+/// `current` with the same region of `fresh`, leaving the rest alone.
+/// Returns the merged page and the regions `current` has lost.
+fn update_regions(current: &str, fresh: &str) -> (String, Vec<String>) {
+    let mut merged = current.to_string();
+    let mut missing = Vec::new();
+    let mut rest = fresh;
+    while let Some(start) = rest.find("<!-- docsgen:begin ") {
+        let after = &rest[start + "<!-- docsgen:begin ".len()..];
+        let Some(name) = after.split_once(" -->").map(|(name, _)| name.to_string()) else {
+            break;
+        };
+        let end_marker = format!("<!-- docsgen:end {name} -->");
+        let Some(end) = rest.find(&end_marker) else {
+            break;
+        };
+        let region = &rest[start..end + end_marker.len()];
+        rest = &rest[end + end_marker.len()..];
+
+        let begin_marker = format!("<!-- docsgen:begin {name} -->");
+        match (merged.find(&begin_marker), merged.find(&end_marker)) {
+            (Some(old_start), Some(old_end)) if old_start < old_end => {
+                merged.replace_range(old_start..old_end + end_marker.len(), region);
+            }
+            _ => missing.push(name),
+        }
+    }
+    (merged, missing)
+}
+
+/// Add `targets` missing from `nav.md` to the end of `[group]`, creating the
+/// group (and the file) when needed. Returns how many were added.
+fn append_nav(nav_path: &Path, group: &str, targets: &[String]) -> Result<usize> {
+    let content = match fs::read_to_string(nav_path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", nav_path.display()));
+        }
+    };
+    let listed: BTreeSet<String> = parse_nav(&content)
+        .into_iter()
+        .map(|entry| entry.target)
+        .collect();
+    let new: Vec<&String> = targets
+        .iter()
+        .filter(|target| !listed.contains(target.as_str()))
+        .collect();
+    if new.is_empty() {
+        return Ok(0);
+    }
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let header = format!("[{group}]");
+    let group_start = lines.iter().position(|line| line.trim() == header);
+    let insert_at = match group_start {
+        Some(start) => {
+            let next_group = lines[start + 1..]
+                .iter()
+                .position(|line| line.trim().starts_with('['))
+                .map_or(lines.len(), |idx| start + 1 + idx);
+            // After the group's last entry, before the blank lines that
+            // separate it from the next one.
+            (start + 1..next_group)
+                .rev()
+                .find(|&idx| !lines[idx].trim().is_empty())
+                .map_or(start + 1, |idx| idx + 1)
+        }
+        None => {
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.len()
+        }
+    };
+    let entries = new.iter().map(|target| format!("- {target}"));
+    lines.splice(insert_at..insert_at, entries);
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    fs::write(nav_path, out).with_context(|| format!("failed to write {}", nav_path.display()))?;
+    Ok(new.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::TempDir;
+
+    #[test]
+    fn cards_become_operations_with_examples_and_schemas() {
+        let root = TempDir::new("openapi", &[]);
+        root.write(
+            "site.md",
+            "title: Arch API\napi_base: https://api.arch.dev/\n",
+        );
+        root.write("en/nav.md", "[Resources]\n- api.md\n- errors.md\n");
+        root.write(
+            "en/api.md",
+            r#"# Create a resource
+
+## Description
+
+> **Sample.** Not a real API.
+
+Creates a resource and `returns` it.
+
+## Architecture
+
+### JSON
+
+#### Request POST /v1/resources
+
+```json
+{
+  "method": "POST",
+  "path": "/v1/resources",
+  "headers": { "Authorization": "Bearer sk_test", "Idempotency-Key": "abc" },
+  "body": { "name": "First", "tags": ["a"], "size": 2 }
+}
+```
+
+#### Response 201
+
+```json
+{ "id": "res_1", "created_at": "2024-01-01T00:00:00Z", "size": 2.5 }
+```
+
+#### Request GET /v1/resources/:id?expand=owner — Fetch one
+
+```json
+{ "method": "GET", "path": "/v1/resources/:id" }
+```
+
+#### Response 404 — No such resource
+
+```json
+{ "error": "not_found" }
+```
+"#,
+        );
+        root.write(
+            "en/errors.md",
+            "# Errors\n\n## Architecture\n\n### JSON\n\n#### Response 429 — Rate limited\n\n```json\n{ \"error\": \"rate_limited\" }\n```\n\n#### Request POST /v1/resources\n\n```json\n{ \"name\": \"x\" }\n```\n\n#### Response 403\n\n```json\n{ \"error\": \"forbidden\" }\n```\n\n#### Request\n\n```json\n{ \"name\": \"no path\" }\n```\n",
+        );
+
+        let doc = export(&root, None, false, "2.0.0").unwrap();
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(doc["info"]["title"], "Arch API");
+        assert_eq!(doc["info"]["version"], "2.0.0");
+        assert_eq!(doc["servers"][0]["url"], "https://api.arch.dev");
+
+        let create = &doc["paths"]["/v1/resources"]["post"];
+        assert_eq!(create["summary"], "Create a resource");
+        assert_eq!(create["description"], "Creates a resource and returns it.");
+        assert_eq!(create["operationId"], "postV1Resources");
+        assert_eq!(create["tags"], json!(["Resources"]));
+        assert_eq!(create["security"], json!([{ "bearerAuth": [] }]));
+        assert_eq!(create["parameters"][0]["name"], "Idempotency-Key");
+        let body = &create["requestBody"]["content"]["application/json"];
+        assert_eq!(body["example"]["name"], "First");
+        assert_eq!(
+            body["schema"]["properties"]["tags"]["items"]["type"],
+            "string"
+        );
+        assert_eq!(body["schema"]["properties"]["size"]["type"], "integer");
+        let created = &create["responses"]["201"];
+        assert_eq!(created["description"], "Created");
+        let schema = &created["content"]["application/json"]["schema"];
+        assert_eq!(schema["properties"]["created_at"]["format"], "date-time");
+        assert_eq!(schema["properties"]["size"]["type"], "number");
+        // errors.md documents the same endpoint: its 403 is added, its body
+        // sample is not.
+        assert_eq!(create["responses"]["403"]["description"], "Forbidden");
+
+        let fetch = &doc["paths"]["/v1/resources/{id}"]["get"];
+        assert_eq!(fetch["summary"], "Fetch one");
+        assert_eq!(fetch["parameters"][0]["in"], "path");
+        assert_eq!(fetch["parameters"][1]["name"], "expand");
+        assert!(fetch.get("requestBody").is_none());
+        assert_eq!(fetch["responses"]["404"]["description"], "No such resource");
+
+        let limited = &doc["components"]["responses"]["RateLimited"];
+        assert_eq!(limited["description"], "Rate limited");
+        assert_eq!(
+            limited["content"]["application/json"]["example"]["error"],
+            "rate_limited"
+        );
+        assert_eq!(
+            doc["components"]["securitySchemes"]["bearerAuth"]["scheme"],
+            "bearer"
+        );
+    }
+
+    #[test]
+    fn form_and_multipart_cards_export_their_media_types_and_path_params() {
+        let root = TempDir::new("openapi-form", &[]);
+        root.write("en/nav.md", "[Tokens]\n- tokens.md\n");
+        root.write(
+            "en/tokens.md",
+            r#"# Tokens
+
+## Architecture
+
+### JSON
+
+#### Request POST /oauth/token
+
+```json
+{
+  "method": "POST",
+  "path": "/oauth/token",
+  "form": { "grant_type": "client_credentials", "scope": ["read", "write"] }
+}
+```
+
+#### Request PUT /v1/users/{id}/avatar — Upload an avatar
+
+```json
+{
+  "method": "PUT",
+  "path": "/v1/users/{id}/avatar",
+  "path_params": { "id": 42 },
+  "multipart": { "file": "@avatar.png", "caption": "Me" }
+}
+```
+"#,
+        );
+
+        let doc = export(&root, None, false, "1.0.0").unwrap();
+        let token = &doc["paths"]["/oauth/token"]["post"]["requestBody"]["content"];
+        let form = &token["application/x-www-form-urlencoded"];
+        assert_eq!(form["schema"]["properties"]["grant_type"]["type"], "string");
+        assert_eq!(form["schema"]["properties"]["scope"]["type"], "array");
+        assert_eq!(form["example"]["scope"], json!(["read", "write"]));
+        assert!(token.get("application/json").is_none());
+
+        let upload = &doc["paths"]["/v1/users/{id}/avatar"]["put"];
+        let param = &upload["parameters"][0];
+        assert_eq!(param["in"], "path");
+        assert_eq!(param["name"], "id");
+        assert_eq!(param["schema"]["type"], "integer");
+        assert_eq!(param["example"], 42);
+        let multipart = &upload["requestBody"]["content"]["multipart/form-data"];
+        assert_eq!(
+            multipart["schema"]["properties"]["file"],
+            json!({ "type": "string", "format": "binary" })
+        );
+        assert_eq!(multipart["example"], json!({ "caption": "Me" }));
+
+        // Imported again, the cards send the same fields.
+        let card = |path: &str, method: &'static str| {
+            let operation = Operation {
+                method,
+                path,
+                op: &doc["paths"][path][method],
+                shared_parameters: &[],
+            };
+            let card = request_card(&doc, &operation, None);
+            let (_, json) = card.split_once("```json\n").unwrap();
+            RequestFields::parse(json.split_once("\n```").unwrap().0).unwrap()
+        };
+        let token = card("/oauth/token", "post");
+        let fields = [
+            ("grant_type", "client_credentials"),
+            ("scope", "read"),
+            ("scope", "write"),
+        ];
+        let fields = fields.map(|(key, value)| (key.to_string(), value.to_string()));
+        assert_eq!(token.body, Some(samples::Body::Form(fields.to_vec())));
+        assert!(token.headers.is_empty());
+        let upload = card("/v1/users/{id}/avatar", "put");
+        assert_eq!(upload.path_params, [("id".to_string(), json!(42))]);
+        let fields = [("caption", "Me"), ("file", "@<file>")];
+        let fields = fields.map(|(key, value)| (key.to_string(), value.to_string()));
+        assert_eq!(upload.body, Some(samples::Body::Multipart(fields.to_vec())));
+    }
+
+    #[test]
+    fn array_samples_merge_into_one_item_schema() {
+        let schema = infer_schema(&json!([{ "id": 1, "name": "a" }, { "id": 2.5 }]));
+        let items = &schema["items"];
+        assert_eq!(items["properties"]["id"]["type"], "number");
+        assert_eq!(items["properties"]["name"]["type"], "string");
+        assert_eq!(items["required"], json!(["id"]));
+        assert_eq!(
+            infer_schema(&json!([1, "a"]))["items"],
+            json!({ "anyOf": [{ "type": "integer" }, { "type": "string" }] })
+        );
+    }
+
+    #[test]
+    fn import_writes_pages_that_update_in_place() {
+        let root = TempDir::new(
+            "import",
+            &[(
+                "docs/en/nav.md",
+                "[Start]\n- welcome.md\n\n[Extra]\n- faq.md\n",
+            )],
+        );
+        let spec = |summary: &str| {
+            format!(
+                r##"openapi: 3.1.0
+info: {{ title: Pets, version: "1" }}
+tags: [{{ name: Pets, description: Pet records. }}]
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name: {{ type: string, description: Shown to owners. }}
+        born: {{ type: string, format: date-time }}
+paths:
+  /pets/{{petId}}:
+    parameters:
+      - {{ name: petId, in: path, required: true, schema: {{ type: string }} }}
+    put:
+      tags: [Pets]
+      summary: {summary}
+      requestBody:
+        content:
+          application/json:
+            schema: {{ $ref: "#/components/schemas/Pet" }}
+      responses:
+        "200":
+          description: The updated pet.
+          content:
+            application/json:
+              example: {{ name: Rex }}
+        "404":
+          description: No such pet
+"##
+            )
+        };
+        root.write("spec.yaml", spec("Update a pet"));
+        let mut args = OpenapiImportArgs {
+            spec: root.join("spec.yaml"),
+            docs_dir: root.join("docs"),
+            lang: "en".to_string(),
+            out: None,
+            by: PageSplit::Tag,
+            group: None,
+            update: false,
+        };
+
+        let report = import(&args).unwrap();
+        assert_eq!(report.written, vec![root.join("docs/en/api/pets.md")]);
+        assert_eq!(report.nav_added, 1);
+        let page_path = root.join("docs/en/api/pets.md");
+        let page = fs::read_to_string(&page_path).unwrap();
+        assert!(page.starts_with("# Pets\n\n## Description\n"));
+        assert!(page.contains("| `petId` | path | string | yes |  |"));
+        assert!(page.contains("| `name` | string | yes | Shown to owners. |"));
+
+        let (_, _, json_md, _) = split_sections(&page);
+        let blocks = build_api_blocks(&json_md, DEFAULT_API_BASE, &[]);
+        let request = &blocks[0];
+        assert_eq!(request.method.as_deref(), Some("PUT"));
+        assert_eq!(request.path.as_deref(), Some("/pets/{petId}"));
+        assert_eq!(request.label, "Update a pet");
+        let sample: Value = serde_json::from_str(&request.raw).unwrap();
+        assert_eq!(sample["path_params"], json!({ "petId": "string" }));
+        assert_eq!(
+            sample["body"],
+            json!({ "name": "string", "born": "2024-01-01T00:00:00Z" })
+        );
+        let statuses: Vec<_> = blocks.iter().filter_map(|b| b.status.as_deref()).collect();
+        assert_eq!(statuses, ["200", "404"]);
+        assert_eq!(
+            blocks.iter().find(|b| b.kind == "response").unwrap().label,
+            "The updated pet"
+        );
+
+        // Without --update an existing page is left alone.
+        assert!(import(&args).is_err());
+
+        let edited = page.replace("## Architecture", "Owners see this.\n\n## Architecture");
+        fs::write(&page_path, edited).unwrap();
+        root.write("spec.yaml", spec("Replace a pet"));
+        args.update = true;
+        let report = import(&args).unwrap();
+        assert_eq!(report.updated.len(), 1);
+        assert_eq!(report.nav_added, 0);
+        let page = fs::read_to_string(&page_path).unwrap();
+        assert!(page.contains("Owners see this.\n\n## Architecture"));
+        assert!(page.contains("#### Request PUT /pets/{petId} — Replace a pet"));
+        assert!(!page.contains("Update a pet"));
+
+        assert_eq!(
+            fs::read_to_string(root.join("docs/en/nav.md")).unwrap(),
+            "[Start]\n- welcome.md\n\n[Extra]\n- faq.md\n\n[Pets]\n- api/pets.md\n"
+        );
+    }
+
+    #[test]
+    fn nav_entries_go_to_the_end_of_an_existing_group() {
+        let root = TempDir::new(
+            "nav",
+            &[("nav.md", "[API]\n- api/a.md\n\n[Other]\n- b.md\n")],
+        );
+        let nav = root.join("nav.md");
+        let added = append_nav(&nav, "API", &["api/a.md".into(), "api/c.md".into()]).unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            fs::read_to_string(&nav).unwrap(),
+            "[API]\n- api/a.md\n- api/c.md\n\n[Other]\n- b.md\n"
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Where a build's files go. `build` and `serve --write` put them under
+//! `out_dir`, swapping a full build in only once it is complete; plain
+//! `serve` keeps them in memory, so a dev session leaves the real build
+//! output alone and a reader never catches the site half-written.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use actix_web::web::{self, Bytes};
+use actix_web::{HttpRequest, HttpResponse};
+use anyhow::{Context, Result, anyhow};
+use walkdir::WalkDir;
+
+use crate::{SiteMeta, assets, not_found_response};
+
+/// Written to the root of a disk build. A directory without it was not made
+/// by docsgen, and is only replaced with `--force`.
+const MARKER: &str = ".docsgen";
+
+/// At most this many unmanaged files are named when refusing to replace a
+/// directory; the rest are counted.
+const LISTED_UNMANAGED: usize = 20;
+
+/// The target of one build. Paths are relative to the site root and
+/// `/`-separated: `en/errors/index.html`.
+pub(crate) enum Output {
+    Disk(DiskOutput),
+    Memory(Mutex<HashMap<String, Bytes>>),
+}
+
+/// `out_dir`, and the sibling directory a full build is written to before
+/// it is renamed into place. Until then the previous build stays whole, and
+/// a build that fails halfway leaves no trace.
+pub(crate) struct DiskOutput {
+    out_dir: PathBuf,
+    force: bool,
+    /// Set between `clear` and `finish` of a full build.
+    staging: Mutex<Option<PathBuf>>,
+}
+
+impl Output {
+    pub(crate) fn disk(out_dir: &Path, force: bool) -> Self {
+        Output::Disk(DiskOutput {
+            out_dir: out_dir.to_path_buf(),
+            force,
+            staging: Mutex::new(None),
+        })
+    }
+
+    /// Start a full build from an empty site. On disk the files go to a
+    /// fresh staging directory next to `out_dir`, and `finish` swaps it in.
+    pub(crate) fn clear(&self) -> Result<()> {
+        match self {
+            Output::Disk(disk) => disk.start(),
+            Output::Memory(files) => {
+                files.lock().unwrap().clear();
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn write(&self, rel: &str, contents: impl Into<Bytes>) -> Result<()> {
+        match self {
+            Output::Disk(disk) => {
+                let path = disk.root().join(rel);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("failed to create {}", parent.display()))?;
+                }
+                fs::write(&path, contents.into())
+                    .with_context(|| format!("failed to write {}", path.display()))
+            }
+            Output::Memory(files) => {
+                files
+                    .lock()
+                    .unwrap()
+                    .insert(rel.to_string(), contents.into());
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn read(&self, rel: &str) -> Result<Bytes> {
+        match self {
+            Output::Disk(disk) => {
+                let path = disk.root().join(rel);
+                fs::read(&path)
+                    .map(Bytes::from)
+                    .with_context(|| format!("failed to read {}", path.display()))
+            }
+            Output::Memory(files) => files
+                .lock()
+                .unwrap()
+                .get(rel)
+                .cloned()
+                .with_context(|| format!("{rel} was not written")),
+        }
+    }
+
+    /// Remove a file, or everything under a directory.
+    pub(crate) fn remove(&self, rel: &str) -> Result<()> {
+        match self {
+            Output::Disk(disk) => {
+                let path = disk.root().join(rel);
+                let removed = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else if path.exists() {
+                    fs::remove_file(&path)
+                } else {
+                    Ok(())
+                };
+                removed.with_context(|| format!("failed to remove {}", path.display()))
+            }
+            Output::Memory(files) => {
+                let dir = format!("{rel}/");
+                files
+                    .lock()
+                    .unwrap()
+                    .retain(|path, _| path != rel && !path.starts_with(&dir));
+                Ok(())
+            }
+        }
+    }
+
+    /// Mark the build as ours and, after a full build on disk, move it into
+    /// place. An incremental rebuild has written straight into `out_dir`.
+    pub(crate) fn finish(&self) -> Result<()> {
+        match self {
+            Output::Disk(disk) => {
+                self.write(MARKER, "managed by docsgen")?;
+                disk.swap_in()
+            }
+            Output::Memory(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn into_files(self) -> HashMap<String, Bytes> {
+        match self {
+            Output::Disk(_) => HashMap::new(),
+            Output::Memory(files) => files.into_inner().unwrap(),
+        }
+    }
+}
+
+impl DiskOutput {
+    fn root(&self) -> PathBuf {
+        self.staging
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| self.out_dir.clone())
+    }
+
+    /// `public` → `.public.docsgen-new` (and `-old` for the swap). `start`
+    /// has made sure `out_dir` ends in a name.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let name = self
+            .out_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let parent = self
+            .out_dir
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        parent.join(format!(".{name}.docsgen-{suffix}"))
+    }
+
+    fn start(&self) -> Result<()> {
+        // `.`, `..` or `/`: there is no parent to stage the build in, and
+        // the directory cannot be renamed out of the way.
+        if self.out_dir.file_name().is_none() {
+            return Err(anyhow!(
+                "--out-dir {} must name a directory, such as ./public",
+                self.out_dir.display()
+            ));
+        }
+        if self.out_dir.exists() && !self.out_dir.join(MARKER).exists() && !self.force {
+            let unmanaged = unmanaged_files(&self.out_dir)?;
+            if !unmanaged.is_empty() {
+                let mut listed: Vec<String> = unmanaged
+                    .iter()
+                    .take(LISTED_UNMANAGED)
+                    .map(|rel| format!("  {rel}"))
+                    .collect();
+                if unmanaged.len() > LISTED_UNMANAGED {
+                    listed.push(format!(
+                        "  … and {} more",
+                        unmanaged.len() - LISTED_UNMANAGED
+                    ));
+                }
+                return Err(anyhow!(
+                    "{} was not created by docsgen; pass --force to replace it and remove:\n{}",
+                    self.out_dir.display(),
+                    listed.join("\n")
+                ));
+            }
+        }
+
+        // Left over from a build that was killed before it could clean up.
+        let staging = self.sibling("new");
+        if staging.exists() {
+            fs::remove_dir_all(&staging)
+                .with_context(|| format!("failed to clean {}", staging.display()))?;
+        }
+        fs::create_dir_all(&staging)
+            .with_context(|| format!("failed to create {}", staging.display()))?;
+        *self.staging.lock().unwrap() = Some(staging);
+        Ok(())
+    }
+
+    fn swap_in(&self) -> Result<()> {
+        let Some(staging) = self.staging.lock().unwrap().take() else {
+            return Ok(());
+        };
+        let old = self.sibling("old");
+        if old.exists() {
+            fs::remove_dir_all(&old)
+                .with_context(|| format!("failed to clean {}", old.display()))?;
+        }
+        if self.out_dir.exists()
+            && let Err(err) = fs::rename(&self.out_dir, &old)
+        {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(err)
+                .with_context(|| format!("failed to move {} aside", self.out_dir.display()));
+        }
+        if let Err(err) = fs::rename(&staging, &self.out_dir) {
+            // Put the previous build back rather than leave nothing.
+            let _ = fs::rename(&old, &self.out_dir);
+            let _ = fs::remove_dir_all(&staging);
+            return Err(err).with_context(|| {
+                format!("failed to move the build into {}", self.out_dir.display())
+            });
+        }
+        if old.exists() {
+            fs::remove_dir_all(&old)
+                .with_context(|| format!("failed to remove {}", old.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DiskOutput {
+    /// A build that failed before `finish` leaves `out_dir` as it was.
+    fn drop(&mut self) {
+        if let Some(staging) = self.staging.get_mut().unwrap().take() {
+            let _ = fs::remove_dir_all(staging);
+        }
+    }
+}
+
+/// Files under `dir`, relative and sorted.
+fn unmanaged_files(dir: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir).min_depth(1) {
+        let entry = entry.with_context(|| format!("failed to read {}", dir.display()))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(dir)?;
+        files.push(rel.to_string_lossy().replace('\\', "/"));
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// What `serve` answers from.
+#[derive(Clone)]
+pub(crate) enum SiteFiles {
+    /// `serve --write`: the files under `out_dir`, rebuilt the way `build`
+    /// does it.
+    Disk {
+        out_dir: PathBuf,
+        force: bool,
+        /// The published build's fingerprinted assets, under `assets/`.
+        hashed_assets: Arc<RwLock<HashSet<String>>>,
+    },
+    /// The last good build. A rebuild renders into a copy (`staging`) and
+    /// `publish` swaps the copy in whole, so a failed rebuild changes nothing.
+    Memory(Arc<RwLock<Published>>),
+}
+
+/// A build held in memory, and its fingerprinted assets under `assets/`.
+#[derive(Default)]
+pub(crate) struct Published {
+    files: HashMap<String, Bytes>,
+    hashed_assets: HashSet<String>,
+}
+
+impl SiteFiles {
+    pub(crate) fn disk(out_dir: &Path, force: bool) -> Self {
+        SiteFiles::Disk {
+            out_dir: out_dir.to_path_buf(),
+            force,
+            hashed_assets: Arc::default(),
+        }
+    }
+
+    pub(crate) fn memory() -> Self {
+        SiteFiles::Memory(Arc::default())
+    }
+
+    pub(crate) fn staging(&self) -> Output {
+        match self {
+            SiteFiles::Disk { out_dir, force, .. } => Output::disk(out_dir, *force),
+            SiteFiles::Memory(site) => {
+                Output::Memory(Mutex::new(site.read().unwrap().files.clone()))
+            }
+        }
+    }
+
+    /// Serve a finished build. The assets manifest is read here, once per
+    /// build, rather than on every request.
+    pub(crate) fn publish(&self, output: Output) {
+        match self {
+            SiteFiles::Disk { hashed_assets, .. } => {
+                *hashed_assets.write().unwrap() =
+                    assets::hashed_paths(self.read(assets::MANIFEST).as_deref());
+            }
+            SiteFiles::Memory(site) => {
+                let files = output.into_files();
+                let hashed_assets =
+                    assets::hashed_paths(files.get(assets::MANIFEST).map(|json| &json[..]));
+                *site.write().unwrap() = Published {
+                    files,
+                    hashed_assets,
+                };
+            }
+        }
+    }
+
+    pub(crate) fn read(&self, rel: &str) -> Option<Bytes> {
+        match self {
+            SiteFiles::Disk { out_dir, .. } => fs::read(out_dir.join(rel)).ok().map(Bytes::from),
+            SiteFiles::Memory(site) => site.read().unwrap().files.get(rel).cloned(),
+        }
+    }
+
+    /// Whether `rel`, relative to `assets/`, is a fingerprinted file of the
+    /// published build.
+    pub(crate) fn is_hashed_asset(&self, rel: &str) -> bool {
+        match self {
+            SiteFiles::Disk { hashed_assets, .. } => hashed_assets.read().unwrap().contains(rel),
+            SiteFiles::Memory(site) => site.read().unwrap().hashed_assets.contains(rel),
+        }
+    }
+}
+
+/// The in-memory counterpart of `actix_files::Files` with `index.html` as
+/// the index file: `/en/errors` and `/en/errors/` both answer with
+/// `en/errors/index.html`.
+pub(crate) async fn serve_file(
+    req: HttpRequest,
+    site: web::Data<SiteMeta>,
+    files: web::Data<SiteFiles>,
+) -> HttpResponse {
+    let path = percent_decode(req.path());
+    let rel = path
+        .strip_prefix(site.base_path.as_str())
+        .unwrap_or(&path)
+        .trim_start_matches('/');
+    let index = match rel.trim_end_matches('/') {
+        "" => "index.html".to_string(),
+        dir => format!("{dir}/index.html"),
+    };
+    for candidate in [rel, index.as_str()] {
+        if candidate.is_empty() || candidate.ends_with('/') {
+            continue;
+        }
+        if let Some(body) = files.read(candidate) {
+            return HttpResponse::Ok()
+                .content_type(content_type_for(candidate))
+                .insert_header(("Cache-Control", "no-cache"))
+                .body(body);
+        }
+    }
+    not_found_response(req.path(), &site, &files)
+}
+
+fn content_type_for(rel: &str) -> String {
+    let ext = Path::new(rel)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let mime = actix_files::file_extension_to_mime(ext);
+    let text = mime.type_() == "text" || matches!(ext, "js" | "json" | "svg" | "xml");
+    if text {
+        format!("{}; charset=utf-8", mime.essence_str())
+    } else {
+        mime.to_string()
+    }
+}
+
+/// `/hi/%E0%A4%B8` → `/hi/स`. Invalid escapes are kept as written.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::TempDir;
+
+    #[test]
+    fn a_staged_rebuild_is_only_served_once_published() {
+        let files = SiteFiles::memory();
+        let first = files.staging();
+        first.write("en/index.html", "one").unwrap();
+        files.publish(first);
+
+        let second = files.staging();
+        second.write("en/index.html", "two").unwrap();
+        assert_eq!(files.read("en/index.html").unwrap(), "one");
+        drop(second);
+        assert_eq!(files.read("en/index.html").unwrap(), "one");
+
+        let third = files.staging();
+        third.clear().unwrap();
+        third.write("hi/index.html", "तीन").unwrap();
+        files.publish(third);
+        assert!(files.read("en/index.html").is_none());
+        assert_eq!(percent_decode("/hi/%E0%A4%A4%zz"), "/hi/त%zz");
+    }
+
+    #[test]
+    fn an_out_dir_without_a_name_is_refused() {
+        let root = TempDir::new("output-unnamed", &[]);
+        for out_dir in [Path::new(".").to_path_buf(), root.join("public/..")] {
+            let err = Output::disk(&out_dir, true).clear().unwrap_err();
+            assert!(format!("{err}").contains("must name a directory"), "{err}");
+        }
+        assert_eq!(fs::read_dir(&*root).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn a_full_build_replaces_the_output_only_once_it_finishes() {
+        let root = TempDir::new("output", &[]);
+        let out_dir = root.join("public");
+        fs::create_dir_all(out_dir.join("old")).unwrap();
+        fs::write(out_dir.join("old/notes.txt"), "mine").unwrap();
+
+        // Somebody else's directory is listed, not removed.
+        let err = Output::disk(&out_dir, false).clear().unwrap_err();
+        assert!(format!("{err}").contains("--force to replace it and remove:\n  old/notes.txt"));
+        assert!(out_dir.join("old/notes.txt").exists());
+
+        let first = Output::disk(&out_dir, true);
+        first.clear().unwrap();
+        first.write("en/index.html", "one").unwrap();
+        first.write("en/gone/index.html", "deleted later").unwrap();
+        assert!(!out_dir.join("en").exists());
+        first.finish().unwrap();
+        assert!(!out_dir.join("old").exists());
+        assert_eq!(
+            fs::read_to_string(out_dir.join("en/index.html")).unwrap(),
+            "one"
+        );
+
+        // A failed build (dropped before `finish`) changes nothing.
+        let failed = Output::disk(&out_dir, false);
+        failed.clear().unwrap();
+        failed.write("en/index.html", "half").unwrap();
+        drop(failed);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("en/index.html")).unwrap(),
+            "one"
+        );
+
+        // Pages that are no longer built do not linger.
+        let second = Output::disk(&out_dir, false);
+        second.clear().unwrap();
+        second.write("en/index.html", "two").unwrap();
+        second.finish().unwrap();
+        assert!(!out_dir.join("en/gone").exists());
+        assert_eq!(
+            fs::read_to_string(out_dir.join("en/index.html")).unwrap(),
+            "two"
+        );
+
+        let leftovers: Vec<_> = fs::read_dir(&*root)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec!["public"]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Live reload for `serve --watch`. The watcher thread records each rebuild
+//! here; open pages hear about it over Server-Sent Events (`/__events`), or
+//! by polling `/__reload` where EventSource is unavailable. Both carry the
+//! same `ReloadStatus`, so a failed rebuild shows up as an overlay in the
+//! browser rather than only in the terminal, and a stylesheet edit is swapped
+//! in without reloading the page.
+
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::web::{self, Bytes};
+use actix_web::{HttpResponse, Responder};
+use futures_util::stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::now_millis;
+
+/// A comment line sent this often keeps proxies and the browser from
+/// dropping an idle event stream.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ReloadStatus {
+    /// Changes on every successful build; a page reloads when it sees a new one.
+    pub(crate) version: u64,
+    /// Changes when a rebuild touched only stylesheets. The page swaps its
+    /// `assets/` stylesheets in place instead of reloading, keeping scroll
+    /// position and open tabs.
+    pub(crate) styles: u64,
+    /// Set while the latest rebuild is failing. The page keeps showing the
+    /// last good output underneath the overlay.
+    pub(crate) error: Option<BuildFailure>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct BuildFailure {
+    /// The first source file named in the error, when there is one.
+    pub(crate) file: Option<String>,
+    pub(crate) message: String,
+    /// The full `anyhow` context chain, outermost first.
+    pub(crate) chain: Vec<String>,
+}
+
+pub(crate) struct ReloadState {
+    status: Mutex<ReloadStatus>,
+    events: broadcast::Sender<ReloadStatus>,
+}
+
+impl ReloadState {
+    pub(crate) fn new() -> Arc<Self> {
+        let (events, _) = broadcast::channel(16);
+        Arc::new(Self {
+            status: Mutex::new(ReloadStatus {
+                version: now_millis(),
+                styles: 0,
+                error: None,
+            }),
+            events,
+        })
+    }
+
+    pub(crate) fn current(&self) -> ReloadStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub(crate) fn succeeded(&self) {
+        self.publish(|status| {
+            status.version = now_millis();
+            status.error = None;
+        });
+    }
+
+    pub(crate) fn styles_changed(&self) {
+        self.publish(|status| {
+            status.styles = now_millis();
+            status.error = None;
+        });
+    }
+
+    pub(crate) fn failed(&self, err: &anyhow::Error) {
+        let failure = BuildFailure::from_error(err);
+        self.publish(|status| status.error = Some(failure));
+    }
+
+    fn publish(&self, update: impl FnOnce(&mut ReloadStatus)) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            update(&mut status);
+            status.clone()
+        };
+        // No open pages means no receivers, which is not an error.
+        let _ = self.events.send(status);
+    }
+}
+
+impl BuildFailure {
+    fn from_error(err: &anyhow::Error) -> Self {
+        let chain: Vec<String> = err.chain().map(|cause| cause.to_string()).collect();
+        let file = chain.iter().find_map(|message| source_file_in(message));
+        Self {
+            file,
+            message: chain.last().cloned().unwrap_or_default(),
+            chain,
+        }
+    }
+}
+
+/// Build errors name their file in the context message ("failed to read
+/// docs/en/a.md", "invalid frontmatter in docs/en/b.md").
+fn source_file_in(message: &str) -> Option<String> {
+    message
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '\'' | '"' | ':' | ',' | '(' | ')')))
+        .find(|word| word.ends_with(".md") || word.ends_with(".html"))
+        .map(str::to_string)
+}
+
+pub(crate) async fn reload_poll(state: web::Data<Arc<ReloadState>>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(state.current())
+}
+
+pub(crate) async fn reload_events(state: web::Data<Arc<ReloadState>>) -> HttpResponse {
+    let state = state.get_ref().clone();
+    let receiver = state.events.subscribe();
+    // The current status goes out first, so a page opened during a failing
+    // build shows the overlay straight away.
+    let first = Some(state.current());
+    let events = stream::unfold(
+        (state, receiver, first),
+        |(state, mut receiver, first)| async move {
+            if let Some(status) = first {
+                return Some((Ok::<_, Infallible>(frame(&status)), (state, receiver, None)));
+            }
+            let bytes = match tokio::time::timeout(KEEP_ALIVE, receiver.recv()).await {
+                Ok(Ok(status)) => frame(&status),
+                // Missed some updates; only the latest matters.
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => frame(&state.current()),
+                Ok(Err(broadcast::error::RecvError::Closed)) => return None,
+                Err(_) => Bytes::from_static(b": keep-alive\n\n"),
+            };
+            Some((Ok(bytes), (state, receiver, None)))
+        },
+    );
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-store"))
+        .streaming(events)
+}
+
+fn frame(status: &ReloadStatus) -> Bytes {
+    let json = serde_json::to_string(status).unwrap_or_else(|_| "{}".to_string());
+    Bytes::from(format!("event: status\ndata: {json}\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn a_failed_rebuild_is_published_until_the_next_success() {
+        let state = ReloadState::new();
+        let mut receiver = state.events.subscribe();
+        let before = state.current().version;
+
+        let err = Err::<(), _>(anyhow!("unclosed frontmatter block"))
+            .context("invalid frontmatter in docs/en/errors.md")
+            .unwrap_err();
+        state.failed(&err);
+        let failed = receiver.try_recv().unwrap();
+        let failure = failed.error.unwrap();
+        assert_eq!(failure.file.as_deref(), Some("docs/en/errors.md"));
+        assert_eq!(failure.message, "unclosed frontmatter block");
+        assert_eq!(failure.chain.len(), 2);
+        assert_eq!(failed.version, before);
+
+        std::thread::sleep(Duration::from_millis(2));
+        state.succeeded();
+        let fixed = receiver.try_recv().unwrap();
+        assert!(fixed.error.is_none());
+        assert!(fixed.version > before);
+
+        // A stylesheet edit is swapped in without a reload.
+        state.styles_changed();
+        let restyled = receiver.try_recv().unwrap();
+        assert_eq!(restyled.version, fixed.version);
+        assert!(restyled.styles > 0);
+    }
+}
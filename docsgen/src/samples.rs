@@ -0,0 +1,1211 @@
+//! Code samples derived from a request block. Each generator turns the same
+//! parsed request — method, URL, headers, body — into one language, and the
+//! JSON tab shows the results as a tabbed card after the request. `site.md`
+//! picks the languages with `code_samples:`; an authored `#### Python` (or any
+//! other language heading) block replaces that language's generated sample.
+
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::ser::{PrettyFormatter, Serializer};
+
+use crate::{dedent_body, split_top_level_json_object, unquote};
+
+/// A request block's fields, read but not yet put together into a call.
+/// The code samples and `docsgen export openapi` both start from these.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct RequestFields {
+    /// Upper-cased.
+    pub(crate) method: Option<String>,
+    /// `path`, or `url` without one, as written.
+    pub(crate) path: Option<String>,
+    pub(crate) path_params: Vec<(String, Value)>,
+    /// `query`, or `params` without one. An array value is one parameter
+    /// repeated per item.
+    pub(crate) query: Vec<(String, Value)>,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Option<Body>,
+}
+
+impl RequestFields {
+    /// Read a request block shaped like
+    /// `{ "method": ..., "path": ..., "path_params": {...}, "query": {...},
+    /// "headers": {...}, "body": {...} }`, where `form` or `multipart` may
+    /// stand in for `body`. Returns `None` when the block has none of
+    /// `method`, `path`, `url`, `headers` or a body field: then the whole
+    /// block is the body.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        const ENVELOPE: [&str; 8] = [
+            "method",
+            "path",
+            "url",
+            "headers",
+            "body",
+            "payload",
+            "form",
+            "multipart",
+        ];
+        let fields = split_top_level_json_object(raw);
+        if !fields
+            .iter()
+            .any(|(key, _)| ENVELOPE.contains(&key.as_str()))
+        {
+            return None;
+        }
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        let body = if let Some(body) = field("body").or_else(|| field("payload")) {
+            Some(Body::Raw(dedent_body(&body)))
+        } else if let Some(form) = field("form") {
+            Some(Body::Form(pairs(&entries(&form))))
+        } else {
+            field("multipart").map(|multipart| Body::Multipart(pairs(&entries(&multipart))))
+        };
+        Some(Self {
+            method: field("method").map(|v| unquote(&v).to_uppercase()),
+            path: field("path").or_else(|| field("url")).map(|v| unquote(&v)),
+            path_params: field("path_params")
+                .map(|raw| entries(&raw))
+                .unwrap_or_default(),
+            query: field("query")
+                .or_else(|| field("params"))
+                .map(|raw| entries(&raw))
+                .unwrap_or_default(),
+            headers: field("headers")
+                .map(|raw| {
+                    split_top_level_json_object(&raw)
+                        .into_iter()
+                        .map(|(key, value)| (key, unquote(&value)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            body,
+        })
+    }
+}
+
+/// The request a block describes. `path_params` are already substituted and
+/// `query` appended, so `url` is the address to call; a JSON `Content-Type`
+/// is added when a raw body has none.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) url: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Option<Body>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Body {
+    /// `body` or `payload`, as the author wrote it, dedented.
+    Raw(String),
+    /// `form`: fields sent URL-encoded.
+    Form(Vec<(String, String)>),
+    /// `multipart`: fields sent as `multipart/form-data`. A value starting
+    /// with `@` names a file to upload, as in curl's `-F`.
+    Multipart(Vec<(String, String)>),
+}
+
+impl Request {
+    /// The call a request block describes, with relative paths joined to
+    /// `api_base`. Returns `None` when there is no path to call.
+    pub(crate) fn parse(raw: &str, api_base: &str) -> Option<Self> {
+        let RequestFields {
+            method,
+            path,
+            path_params,
+            query,
+            mut headers,
+            body,
+        } = RequestFields::parse(raw)?;
+        let path = path.filter(|path| !path.is_empty())?;
+        let path = fill_path_params(&path, &pairs(&path_params));
+        let mut url = if path.starts_with("http://") || path.starts_with("https://") {
+            path
+        } else if path.starts_with('/') {
+            format!("{api_base}{path}")
+        } else {
+            format!("{api_base}/{path}")
+        };
+        let query = pairs(&query);
+        if !query.is_empty() {
+            let encoded: Vec<String> = query
+                .iter()
+                .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+                .collect();
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(&encoded.join("&"));
+        }
+        if matches!(body, Some(Body::Raw(_))) && content_type(&headers).is_none() {
+            headers.push(("Content-Type".to_string(), "application/json".to_string()));
+        }
+
+        Some(Self {
+            method: method.unwrap_or_else(|| "GET".to_string()),
+            url,
+            headers,
+            body,
+        })
+    }
+
+    /// The raw body parsed as JSON, when it is JSON and sent as JSON.
+    fn json_body(&self) -> Option<Value> {
+        let is_json = content_type(&self.headers).is_none_or(|ct| ct.contains("json"));
+        match &self.body {
+            Some(Body::Raw(body)) if is_json => serde_json::from_str(body).ok(),
+            _ => None,
+        }
+    }
+
+    /// Headers to write out, minus a `Content-Type` the client sets itself:
+    /// always for form and multipart bodies, and for JSON when the client
+    /// has a JSON body helper.
+    fn headers(&self, native_json: bool) -> Vec<&(String, String)> {
+        let drop_type = match &self.body {
+            Some(Body::Form(_) | Body::Multipart(_)) => true,
+            Some(Body::Raw(_)) => native_json && self.json_body().is_some(),
+            None => false,
+        };
+        self.headers
+            .iter()
+            .filter(|(key, _)| !(drop_type && key.eq_ignore_ascii_case("content-type")))
+            .collect()
+    }
+}
+
+fn content_type(headers: &[(String, String)]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+}
+
+/// An object's entries. Outside strict JSON, each value that does not parse
+/// on its own is kept as text.
+fn entries(raw: &str) -> Vec<(String, Value)> {
+    match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(fields)) => fields.into_iter().collect(),
+        _ => split_top_level_json_object(raw)
+            .into_iter()
+            .map(|(key, value)| {
+                let value = serde_json::from_str(&value).unwrap_or(Value::String(unquote(&value)));
+                (key, value)
+            })
+            .collect(),
+    }
+}
+
+/// Entries as text. Strings lose their quotes, other values keep their JSON
+/// form, and an array repeats its key once per item.
+fn pairs(entries: &[(String, Value)]) -> Vec<(String, String)> {
+    let text = |value: &Value| match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    entries
+        .iter()
+        .flat_map(|(key, value)| match value {
+            Value::Array(items) => items.iter().map(|item| (key.clone(), text(item))).collect(),
+            other => vec![(key.clone(), text(other))],
+        })
+        .collect()
+}
+
+/// `/v1/users/{id}` and `/v1/users/:id` with `id` filled in. Parameters the
+/// path does not mention are ignored, and unfilled ones stay as written.
+fn fill_path_params(path: &str, params: &[(String, String)]) -> String {
+    let mut path = path.to_string();
+    for (name, value) in params {
+        let value = percent_encode(value);
+        path = path.replace(&format!("{{{name}}}"), &value);
+        let colon = format!(":{name}");
+        path = path
+            .split('/')
+            .map(|segment| {
+                if segment == colon {
+                    value.as_str()
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+    path
+}
+
+/// Percent-encode everything but RFC 3986's unreserved characters.
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// The file a multipart `@path` value uploads.
+fn file_path(value: &str) -> Option<&str> {
+    value.strip_prefix('@').filter(|path| !path.is_empty())
+}
+
+/// Repeated keys gathered under their first position, for the languages
+/// whose maps cannot hold a key twice.
+fn grouped(fields: &[(String, String)]) -> Vec<(&str, Vec<&str>)> {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (key, value) in fields {
+        match groups.iter_mut().find(|(name, _)| name == key) {
+            Some((_, values)) => values.push(value),
+            None => groups.push((key, vec![value])),
+        }
+    }
+    groups
+}
+
+/// One language a request can be written in.
+pub(crate) struct Generator {
+    /// Block kind and `code_samples:` name.
+    pub(crate) id: &'static str,
+    /// Tab label, and the `####` heading an authored sample uses.
+    pub(crate) label: &'static str,
+    /// Fence language, which picks the highlighter.
+    pub(crate) lang: &'static str,
+    /// Other `code_samples:` names that mean this language. Headings never
+    /// use them, so `#### Requests` or `#### Bash` stays an ordinary card.
+    aliases: &'static [&'static str],
+    generate: fn(&Request) -> String,
+}
+
+impl Generator {
+    pub(crate) fn generate(&self, request: &Request) -> String {
+        (self.generate)(request)
+    }
+}
+
+/// Every generator, in the default tab order.
+pub(crate) static GENERATORS: [Generator; 7] = [
+    Generator {
+        id: "curl",
+        label: "cURL",
+        lang: "bash",
+        aliases: &["bash", "shell"],
+        generate: curl,
+    },
+    Generator {
+        id: "python",
+        label: "Python",
+        lang: "python",
+        aliases: &["py", "requests"],
+        generate: python,
+    },
+    Generator {
+        id: "javascript",
+        label: "JavaScript",
+        lang: "javascript",
+        aliases: &["js", "fetch"],
+        generate: javascript,
+    },
+    Generator {
+        id: "go",
+        label: "Go",
+        lang: "go",
+        aliases: &["golang"],
+        generate: go,
+    },
+    Generator {
+        id: "rust",
+        label: "Rust",
+        lang: "rust",
+        aliases: &["reqwest"],
+        generate: rust,
+    },
+    Generator {
+        id: "httpie",
+        label: "HTTPie",
+        lang: "bash",
+        aliases: &[],
+        generate: httpie,
+    },
+    Generator {
+        id: "powershell",
+        label: "PowerShell",
+        lang: "powershell",
+        aliases: &["pwsh"],
+        generate: powershell,
+    },
+];
+
+/// The generator a `####` heading names: its id or label alone, in any case.
+pub(crate) fn for_heading(text: &str) -> Option<&'static Generator> {
+    let text = text.trim();
+    GENERATORS.iter().find(|generator| {
+        text.eq_ignore_ascii_case(generator.id) || text.eq_ignore_ascii_case(generator.label)
+    })
+}
+
+/// The generator a block kind or `code_samples:` entry names, in any case.
+pub(crate) fn find(name: &str) -> Option<&'static Generator> {
+    let name = name.trim().to_lowercase();
+    GENERATORS
+        .iter()
+        .find(|generator| generator.id == name || generator.aliases.contains(&name.as_str()))
+}
+
+/// Resolve `code_samples:` from site.md: every language when unset, none for
+/// an empty list or `none`. Unknown names are skipped with one warning each.
+pub(crate) fn languages(names: Option<&[String]>) -> Vec<&'static Generator> {
+    let Some(names) = names else {
+        return GENERATORS.iter().collect();
+    };
+    let mut picked: Vec<&'static Generator> = Vec::new();
+    for name in names {
+        if name.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        match find(name) {
+            Some(generator) => {
+                if !picked.iter().any(|p| p.id == generator.id) {
+                    picked.push(generator);
+                }
+            }
+            None => warn_unknown(name),
+        }
+    }
+    picked
+}
+
+/// `serve --watch` re-reads site.md on every rebuild, so each bad name is
+/// reported once.
+fn warn_unknown(name: &str) {
+    static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let warned = WARNED.get_or_init(|| Mutex::new(HashSet::new()));
+    if let Ok(mut seen) = warned.lock()
+        && seen.insert(name.to_string())
+    {
+        let known: Vec<&str> = GENERATORS.iter().map(|g| g.id).collect();
+        eprintln!(
+            "warning: unknown code sample language '{name}' in site.md. Available: {}",
+            known.join(", ")
+        );
+    }
+}
+
+// ─── Generators ──────────────────────────────────────────────────────────────
+
+/// Every value is quoted for a POSIX shell: the URL when it carries a query
+/// or anything else the shell would read, headers inside double quotes, and
+/// bodies and fields inside single quotes.
+fn curl(request: &Request) -> String {
+    let mut out = format!("curl -X {} {}", request.method, shell_quote(&request.url));
+    for (key, value) in request.headers(false) {
+        out.push_str(" \\\n  -H ");
+        out.push_str(&double_quote(&format!("{key}: {value}")));
+    }
+    match &request.body {
+        Some(Body::Raw(body)) => {
+            out.push_str(" \\\n  -d ");
+            out.push_str(&single_quote(&indent_continuation(body, "  ")));
+        }
+        Some(Body::Form(fields)) => {
+            for (key, value) in fields {
+                out.push_str(" \\\n  --data-urlencode ");
+                out.push_str(&shell_quote(&format!("{key}={value}")));
+            }
+        }
+        Some(Body::Multipart(fields)) => {
+            for (key, value) in fields {
+                out.push_str(" \\\n  -F ");
+                out.push_str(&shell_quote(&format!("{key}={value}")));
+            }
+        }
+        None => {}
+    }
+    out
+}
+
+fn python(request: &Request) -> String {
+    let method = request.method.to_lowercase();
+    let call = if matches!(
+        method.as_str(),
+        "get" | "post" | "put" | "patch" | "delete" | "head" | "options"
+    ) {
+        format!("requests.{method}(\n")
+    } else {
+        format!("requests.request(\n    {},\n", quoted(&request.method))
+    };
+
+    let mut out = format!(
+        "import requests\n\nresponse = {call}    {},\n",
+        quoted(&request.url)
+    );
+    let headers = request.headers(true);
+    if !headers.is_empty() {
+        out.push_str("    headers={\n");
+        for (key, value) in headers {
+            out.push_str(&format!("        {}: {},\n", quoted(key), quoted(value)));
+        }
+        out.push_str("    },\n");
+    }
+    let dict = |name: &str, fields: &[(&str, Vec<String>)]| {
+        let mut out = format!("    {name}={{\n");
+        for (key, values) in fields {
+            let value = match values.as_slice() {
+                [one] => one.clone(),
+                many => format!("[{}]", many.join(", ")),
+            };
+            out.push_str(&format!("        {}: {value},\n", quoted(key)));
+        }
+        out.push_str("    },\n");
+        out
+    };
+    match &request.body {
+        Some(Body::Raw(body)) => match request.json_body() {
+            Some(json) => out.push_str(&format!("    json={},\n", python_literal(&json, 1))),
+            None => out.push_str(&format!("    data={},\n", quoted(body))),
+        },
+        Some(Body::Form(fields)) => {
+            let fields: Vec<(&str, Vec<String>)> = grouped(fields)
+                .into_iter()
+                .map(|(key, values)| (key, values.into_iter().map(quoted).collect()))
+                .collect();
+            out.push_str(&dict("data", &fields));
+        }
+        Some(Body::Multipart(fields)) => {
+            let (files, text): (Vec<_>, Vec<_>) = grouped(fields)
+                .into_iter()
+                .partition(|(_, values)| values.iter().any(|value| file_path(value).is_some()));
+            let text: Vec<(&str, Vec<String>)> = text
+                .into_iter()
+                .map(|(key, values)| (key, values.into_iter().map(quoted).collect()))
+                .collect();
+            let files: Vec<(&str, Vec<String>)> = files
+                .into_iter()
+                .map(|(key, values)| {
+                    let opened = values
+                        .into_iter()
+                        .map(|value| {
+                            let path = file_path(value).unwrap_or(value);
+                            format!("open({}, \"rb\")", quoted(path))
+                        })
+                        .collect();
+                    (key, opened)
+                })
+                .collect();
+            if !text.is_empty() {
+                out.push_str(&dict("data", &text));
+            }
+            if !files.is_empty() {
+                out.push_str(&dict("files", &files));
+            }
+        }
+        None => {}
+    }
+    out.push_str(")\nprint(response.json())");
+    out
+}
+
+fn javascript(request: &Request) -> String {
+    let mut out = String::new();
+    let mut options = Vec::new();
+    if request.method != "GET" {
+        options.push(format!("  method: {},", quoted(&request.method)));
+    }
+    let headers = request.headers(false);
+    if !headers.is_empty() {
+        let mut block = String::from("  headers: {\n");
+        for (key, value) in headers {
+            block.push_str(&format!("    {}: {},\n", quoted(key), quoted(value)));
+        }
+        block.push_str("  },");
+        options.push(block);
+    }
+    match &request.body {
+        Some(Body::Raw(body)) => match request.json_body() {
+            Some(json) => options.push(format!(
+                "  body: JSON.stringify({}),",
+                indent_continuation(&pretty_json(&json, b"  "), "  ")
+            )),
+            None => options.push(format!("  body: {},", quoted(body))),
+        },
+        Some(Body::Form(fields)) => {
+            let mut block = String::from("  body: new URLSearchParams([\n");
+            for (key, value) in fields {
+                block.push_str(&format!("    [{}, {}],\n", quoted(key), quoted(value)));
+            }
+            block.push_str("  ]),");
+            options.push(block);
+        }
+        Some(Body::Multipart(fields)) => {
+            if fields.iter().any(|(_, value)| file_path(value).is_some()) {
+                out.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+            }
+            out.push_str("const form = new FormData();\n");
+            for (key, value) in fields {
+                match file_path(value) {
+                    Some(path) => {
+                        let name = path.rsplit('/').next().unwrap_or(path);
+                        out.push_str(&format!(
+                            "form.append({}, await openAsBlob({}), {});\n",
+                            quoted(key),
+                            quoted(path),
+                            quoted(name)
+                        ));
+                    }
+                    None => out.push_str(&format!(
+                        "form.append({}, {});\n",
+                        quoted(key),
+                        quoted(value)
+                    )),
+                }
+            }
+            out.push('\n');
+            options.push("  body: form,".to_string());
+        }
+        None => {}
+    }
+
+    let url = quoted(&request.url);
+    let call = if options.is_empty() {
+        format!("fetch({url})")
+    } else {
+        format!("fetch({url}, {{\n{}\n}})", options.join("\n"))
+    };
+    out.push_str(&format!(
+        "const response = await {call};\nconst data = await response.json();"
+    ));
+    out
+}
+
+/// Statements only, with the `import` block they need above them.
+fn go(request: &Request) -> String {
+    const CHECK: &str = "if err != nil {\n\tlog.Fatal(err)\n}\n";
+    let mut imports = vec!["log", "net/http"];
+    let mut out = String::new();
+    let mut content_type = None;
+    let body = match &request.body {
+        Some(Body::Raw(body)) if !body.contains('`') => {
+            imports.push("strings");
+            out.push_str(&format!("body := strings.NewReader(`{body}`)\n"));
+            "body"
+        }
+        Some(Body::Raw(body)) => {
+            imports.push("strings");
+            out.push_str(&format!("body := strings.NewReader({})\n", quoted(body)));
+            "body"
+        }
+        Some(Body::Form(fields)) => {
+            imports.extend(["net/url", "strings"]);
+            out.push_str("form := url.Values{}\n");
+            for (key, value) in fields {
+                out.push_str(&format!("form.Add({}, {})\n", quoted(key), quoted(value)));
+            }
+            out.push_str("body := strings.NewReader(form.Encode())\n\n");
+            content_type = Some(quoted("application/x-www-form-urlencoded"));
+            "body"
+        }
+        Some(Body::Multipart(fields)) => {
+            imports.extend(["bytes", "mime/multipart"]);
+            out.push_str("var body bytes.Buffer\nform := multipart.NewWriter(&body)\n");
+            let mut opened = false;
+            for (key, value) in fields {
+                let Some(path) = file_path(value) else {
+                    out.push_str(&format!(
+                        "form.WriteField({}, {})\n",
+                        quoted(key),
+                        quoted(value)
+                    ));
+                    continue;
+                };
+                let assign = if opened { "=" } else { ":=" };
+                if !opened {
+                    imports.extend(["io", "os", "path/filepath"]);
+                }
+                opened = true;
+                out.push_str(&format!(
+                    "file, err {assign} os.Open({})\n{CHECK}",
+                    quoted(path)
+                ));
+                out.push_str(&format!(
+                    "part, err {assign} form.CreateFormFile({}, filepath.Base(file.Name()))\n{CHECK}",
+                    quoted(key)
+                ));
+                out.push_str("io.Copy(part, file)\nfile.Close()\n");
+            }
+            out.push_str("form.Close()\n\n");
+            content_type = Some("form.FormDataContentType()".to_string());
+            "&body"
+        }
+        None => "nil",
+    };
+    let method = match request.method.as_str() {
+        "GET" | "HEAD" | "POST" | "PUT" | "PATCH" | "DELETE" | "CONNECT" | "OPTIONS" | "TRACE" => {
+            format!("http.Method{}", title_case(&request.method))
+        }
+        other => quoted(other),
+    };
+    out.push_str(&format!(
+        "req, err := http.NewRequest({method}, {}, {body})\n{CHECK}",
+        quoted(&request.url)
+    ));
+    for (key, value) in request.headers(false) {
+        out.push_str(&format!(
+            "req.Header.Set({}, {})\n",
+            quoted(key),
+            quoted(value)
+        ));
+    }
+    if let Some(content_type) = content_type {
+        out.push_str(&format!(
+            "req.Header.Set(\"Content-Type\", {content_type})\n"
+        ));
+    }
+    out.push_str(&format!(
+        "\nresp, err := http.DefaultClient.Do(req)\n{CHECK}"
+    ));
+    out.push_str("defer resp.Body.Close()");
+
+    // Sorted, the way gofmt leaves them.
+    imports.sort_unstable();
+    let mut block = String::from("import (\n");
+    for import in imports {
+        block.push_str(&format!("\t{}\n", quoted(import)));
+    }
+    block.push_str(")\n\n");
+    block + &out
+}
+
+/// `reqwest::Method` has constants for the standard verbs only; any other
+/// is built with `Method::from_bytes`.
+fn rust(request: &Request) -> String {
+    let method = request.method.to_lowercase();
+    let url = quoted(&request.url);
+    let call = match request.method.as_str() {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" => format!(".{method}({url})"),
+        "OPTIONS" | "CONNECT" | "TRACE" => {
+            format!(".request(reqwest::Method::{}, {url})", request.method)
+        }
+        other => format!(
+            ".request(reqwest::Method::from_bytes(b{})?, {url})",
+            quoted(other)
+        ),
+    };
+
+    let mut out = String::new();
+    if let Some(Body::Multipart(fields)) = &request.body {
+        out.push_str("let form = reqwest::multipart::Form::new()");
+        for (key, value) in fields {
+            match file_path(value) {
+                Some(path) => out.push_str(&format!(
+                    "\n    .file({}, {})\n    .await?",
+                    quoted(key),
+                    quoted(path)
+                )),
+                None => out.push_str(&format!("\n    .text({}, {})", quoted(key), quoted(value))),
+            }
+        }
+        out.push_str(";\n");
+    }
+    out.push_str(&format!(
+        "let client = reqwest::Client::new();\nlet response = client\n    {call}\n"
+    ));
+    for (key, value) in request.headers(true) {
+        out.push_str(&format!(
+            "    .header({}, {})\n",
+            quoted(key),
+            quoted(value)
+        ));
+    }
+    match &request.body {
+        Some(Body::Raw(body)) => match request.json_body() {
+            Some(json) => out.push_str(&format!(
+                "    .json(&serde_json::json!({}))\n",
+                indent_continuation(&pretty_json(&json, b"    "), "    ")
+            )),
+            None => out.push_str(&format!("    .body({})\n", quoted(body))),
+        },
+        Some(Body::Form(fields)) => {
+            let pairs: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("({}, {})", quoted(key), quoted(value)))
+                .collect();
+            out.push_str(&format!("    .form(&[{}])\n", pairs.join(", ")));
+        }
+        Some(Body::Multipart(_)) => out.push_str("    .multipart(form)\n"),
+        None => {}
+    }
+    out.push_str("    .send()\n    .await?;");
+    out
+}
+
+/// A JSON object body becomes HTTPie request items — `key=string` for
+/// strings, `key:=json` for anything else — and any other raw body is sent
+/// with `--raw`. Form and multipart fields use `--form` and `--multipart`.
+fn httpie(request: &Request) -> String {
+    let flag = match &request.body {
+        Some(Body::Form(_)) => "--form ",
+        Some(Body::Multipart(_)) => "--multipart ",
+        _ => "",
+    };
+    let mut args = vec![format!(
+        "http {flag}{} {}",
+        request.method,
+        shell_quote(&request.url)
+    )];
+    for (key, value) in request.headers(true) {
+        args.push(format!("{}:{}", shell_quote(key), shell_quote(value)));
+    }
+    match &request.body {
+        Some(Body::Raw(body)) => match request.json_body() {
+            Some(Value::Object(fields)) => {
+                for (key, value) in fields {
+                    let key = shell_quote(&httpie_key(&key));
+                    args.push(match value {
+                        Value::String(text) => format!("{key}={}", shell_quote(&text)),
+                        other => format!("{key}:={}", shell_quote(&other.to_string())),
+                    });
+                }
+            }
+            _ => args.push(format!("--raw {}", shell_quote(body))),
+        },
+        Some(Body::Form(fields) | Body::Multipart(fields)) => {
+            for (key, value) in fields {
+                let key = shell_quote(&httpie_key(key));
+                args.push(match file_path(value) {
+                    Some(path) => format!("{key}@{}", shell_quote(path)),
+                    None => format!("{key}={}", shell_quote(value)),
+                });
+            }
+        }
+        None => {}
+    }
+    args.join(" \\\n  ")
+}
+
+/// Form fields go in a hashtable passed as `-Body`, which PowerShell sends
+/// URL-encoded; multipart fields use `-Form` (PowerShell 7), with files
+/// opened by `Get-Item`. `-Method` only takes the verbs of the
+/// `WebRequestMethod` enum; any other goes through `-CustomMethod`.
+fn powershell(request: &Request) -> String {
+    let mut out = String::new();
+    let method = match request.method.as_str() {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" | "OPTIONS" => {
+            format!("-Method {}", title_case(&request.method))
+        }
+        other => format!("-CustomMethod {}", ps_quote(other)),
+    };
+    let mut params = vec![method, format!("-Uri {}", ps_quote(&request.url))];
+
+    let headers: Vec<&(String, String)> = request
+        .headers
+        .iter()
+        .filter(|(key, _)| !key.eq_ignore_ascii_case("content-type"))
+        .collect();
+    if !headers.is_empty() {
+        out.push_str("$headers = @{\n");
+        for (key, value) in headers {
+            out.push_str(&format!("    {} = {}\n", ps_quote(key), ps_quote(value)));
+        }
+        out.push_str("}\n");
+        params.push("-Headers $headers".to_string());
+    }
+    let table = |fields: &[(String, String)]| {
+        let mut out = String::from("$form = @{\n");
+        for (key, values) in grouped(fields) {
+            let values: Vec<String> = values
+                .into_iter()
+                .map(|value| match file_path(value) {
+                    Some(path) => format!("(Get-Item {})", ps_quote(path)),
+                    None => ps_quote(value),
+                })
+                .collect();
+            let value = match values.as_slice() {
+                [one] => one.clone(),
+                many => format!("@({})", many.join(", ")),
+            };
+            out.push_str(&format!("    {} = {value}\n", ps_quote(key)));
+        }
+        out.push_str("}\n");
+        out
+    };
+    match &request.body {
+        Some(Body::Raw(body)) => {
+            if let Some(content_type) = content_type(&request.headers) {
+                params.push(format!("-ContentType {}", ps_quote(content_type)));
+            }
+            // A here-string ends at a line starting with `'@`.
+            if body.lines().any(|line| line.starts_with("'@")) {
+                out.push_str(&format!("$body = {}\n", ps_quote(body)));
+            } else {
+                out.push_str(&format!("$body = @'\n{body}\n'@\n"));
+            }
+            params.push("-Body $body".to_string());
+        }
+        Some(Body::Form(fields)) => {
+            out.push_str(&table(fields));
+            params.push("-Body $form".to_string());
+        }
+        Some(Body::Multipart(fields)) => {
+            out.push_str(&table(fields));
+            params.push("-Form $form".to_string());
+        }
+        None => {}
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str("Invoke-RestMethod ");
+    out.push_str(&params.join(" `\n    "));
+    out
+}
+
+// ─── Literals ────────────────────────────────────────────────────────────────
+
+/// A double-quoted string literal. JSON's escapes are valid in Python,
+/// JavaScript, Go and Rust alike.
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_else(|_| format!("\"{text}\""))
+}
+
+/// Single-quote for a POSIX shell unless every character is safe bare, so
+/// `&`, `?`, spaces and quotes in a URL or value reach the program intact.
+fn shell_quote(text: &str) -> String {
+    let bare = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:,@%+=~".contains(c));
+    if bare {
+        text.to_string()
+    } else {
+        single_quote(text)
+    }
+}
+
+/// Nothing is special inside single quotes, so a `'` has to close the
+/// string, add an escaped quote and reopen it: `'\''`.
+fn single_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Inside double quotes the shell still reads `\`, `"`, `$` and backticks.
+fn double_quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '"' | '$' | '`') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out.push('"');
+    out
+}
+
+/// PowerShell single-quoted strings only escape `'`, by doubling it.
+fn ps_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// HTTPie reads `=`, `:`, `@` and `[` in a field name as separators or
+/// nesting unless they are backslash-escaped.
+fn httpie_key(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for ch in key.chars() {
+        if matches!(ch, '=' | ':' | '@' | '[' | ']' | '\\') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// `POST` → `Post`.
+fn title_case(word: &str) -> String {
+    let lower = word.to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}", first.to_uppercase(), chars.as_str()),
+        None => String::new(),
+    }
+}
+
+/// Indent every line after the first, for a value that continues an
+/// already-indented line.
+fn indent_continuation(text: &str, indent: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx == 0 || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn pretty_json(value: &Value, indent: &[u8]) -> String {
+    let mut buf = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut buf, PrettyFormatter::with_indent(indent));
+    match value.serialize(&mut serializer) {
+        Ok(()) => String::from_utf8_lossy(&buf).into_owned(),
+        Err(_) => value.to_string(),
+    }
+}
+
+/// JSON as a Python literal, four-space indented with trailing commas.
+fn python_literal(value: &Value, depth: usize) -> String {
+    let pad = "    ".repeat(depth + 1);
+    let close = "    ".repeat(depth);
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => quoted(text),
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Object(fields) if fields.is_empty() => "{}".to_string(),
+        Value::Array(items) => {
+            let lines: Vec<String> = items
+                .iter()
+                .map(|item| format!("{pad}{},", python_literal(item, depth + 1)))
+                .collect();
+            format!("[\n{}\n{close}]", lines.join("\n"))
+        }
+        Value::Object(fields) => {
+            let lines: Vec<String> = fields
+                .iter()
+                .map(|(key, item)| {
+                    format!("{pad}{}: {},", quoted(key), python_literal(item, depth + 1))
+                })
+                .collect();
+            format!("{{\n{}\n{close}}}", lines.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> Request {
+        let raw = "{\n  \"method\": \"POST\",\n  \"path\": \"/v1/items\",\n  \"headers\": { \"Authorization\": \"Bearer it's\" },\n  \"body\": {\n    \"name\": \"Lamp\",\n    \"count\": 2,\n    \"active\": true,\n    \"tags\": null\n  }\n}";
+        Request::parse(raw, "https://api.test").expect("request")
+    }
+
+    #[test]
+    fn every_language_carries_the_method_url_headers_and_body() {
+        let request = request();
+        assert_eq!(request.url, "https://api.test/v1/items");
+        for generator in &GENERATORS {
+            let code = generator.generate(&request);
+            assert!(
+                code.contains("api.test/v1/items"),
+                "{}: {code}",
+                generator.id
+            );
+            assert!(code.contains("Authorization"), "{}: {code}", generator.id);
+            assert!(code.contains("Lamp"), "{}: {code}", generator.id);
+        }
+
+        let python = python(&request);
+        assert!(python.contains("response = requests.post(\n"));
+        assert!(
+            python.contains(
+                "        \"count\": 2,\n        \"active\": True,\n        \"tags\": None,"
+            )
+        );
+        assert!(
+            !python.contains("Content-Type"),
+            "requests sets it for json="
+        );
+
+        let js = javascript(&request);
+        assert!(js.contains("  method: \"POST\",\n"));
+        assert!(js.contains("  body: JSON.stringify({\n    \"name\": \"Lamp\","));
+
+        assert!(
+            go(&request)
+                .contains("http.NewRequest(http.MethodPost, \"https://api.test/v1/items\", body)")
+        );
+        assert!(rust(&request).contains("    .post(\"https://api.test/v1/items\")\n"));
+
+        let httpie = httpie(&request);
+        assert!(httpie.starts_with(
+            "http POST https://api.test/v1/items \\\n  Authorization:'Bearer it'\\''s'"
+        ));
+        assert!(httpie.contains("name=Lamp \\\n  count:=2 \\\n  active:=true \\\n  tags:=null"));
+
+        let ps = powershell(&request);
+        assert!(ps.contains("    'Authorization' = 'Bearer it''s'\n"));
+        assert!(ps.contains("-Method Post `\n    -Uri 'https://api.test/v1/items'"));
+        assert!(ps.contains("-ContentType 'application/json'"));
+    }
+
+    #[test]
+    fn curl_is_shell_safe_and_fills_in_query_and_path_params() {
+        let raw = r#"{
+  "method": "PATCH",
+  "path": "/v1/users/:id/keys/{key}",
+  "path_params": { "id": "u 1", "key": "k1" },
+  "query": { "expand": ["owner", "team"], "q": "a&b" },
+  "headers": { "X-Note": "say \"hi\" for $5" },
+  "body": { "name": "Bob's key" }
+}"#;
+        let request = Request::parse(raw, "https://api.test").unwrap();
+        assert_eq!(
+            request.url,
+            "https://api.test/v1/users/u%201/keys/k1?expand=owner&expand=team&q=a%26b"
+        );
+        let curl = curl(&request);
+        assert!(curl.starts_with(
+            "curl -X PATCH 'https://api.test/v1/users/u%201/keys/k1?expand=owner&expand=team&q=a%26b'"
+        ));
+        assert!(curl.contains(r#"-H "X-Note: say \"hi\" for \$5""#));
+        assert!(curl.contains(r#"-d '{ "name": "Bob'\''s key" }'"#));
+    }
+
+    #[test]
+    fn form_and_multipart_bodies_use_each_client_form_support() {
+        let form = Request::parse(
+            r#"{ "method": "POST", "path": "/token", "form": { "grant_type": "client_credentials", "scope": "read write" } }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        assert!(
+            form.headers.is_empty(),
+            "the client sets the form content type"
+        );
+        let curl_form = curl(&form);
+        assert!(curl_form.contains(
+            "--data-urlencode grant_type=client_credentials \\\n  --data-urlencode 'scope=read write'"
+        ));
+        assert!(httpie(&form).starts_with("http --form POST https://api.test/token"));
+        assert!(
+            go(&form).contains(
+                "req.Header.Set(\"Content-Type\", \"application/x-www-form-urlencoded\")"
+            )
+        );
+
+        let upload = Request::parse(
+            r#"{ "method": "POST", "path": "/files", "multipart": { "purpose": "avatar", "file": "@me.png" } }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        assert!(curl(&upload).ends_with("-F purpose=avatar \\\n  -F file=@me.png"));
+        assert!(
+            python(&upload)
+                .contains("    files={\n        \"file\": open(\"me.png\", \"rb\"),\n    },")
+        );
+        assert!(httpie(&upload).ends_with("purpose=avatar \\\n  file@me.png"));
+        assert!(powershell(&upload).contains("    'file' = (Get-Item 'me.png')\n"));
+        for generator in &GENERATORS {
+            assert!(
+                generator.generate(&upload).contains("me.png"),
+                "{}",
+                generator.id
+            );
+        }
+    }
+
+    #[test]
+    fn go_imports_the_packages_it_uses() {
+        let get = Request::parse(
+            r#"{ "method": "GET", "path": "/items" }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        assert!(go(&get).starts_with("import (\n\t\"log\"\n\t\"net/http\"\n)\n\nreq, err :="));
+
+        let form = Request::parse(
+            r#"{ "method": "POST", "path": "/token", "form": { "scope": "read" } }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        assert!(go(&form).starts_with(
+            "import (\n\t\"log\"\n\t\"net/http\"\n\t\"net/url\"\n\t\"strings\"\n)\n\n"
+        ));
+
+        let upload = Request::parse(
+            r#"{ "method": "POST", "path": "/files", "multipart": { "a": "@a.png", "b": "@b.png" } }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        let imports = [
+            "bytes",
+            "io",
+            "log",
+            "mime/multipart",
+            "net/http",
+            "os",
+            "path/filepath",
+        ];
+        let block: String = imports.iter().map(|i| format!("\t\"{i}\"\n")).collect();
+        assert!(go(&upload).starts_with(&format!("import (\n{block})\n\n")));
+    }
+
+    #[test]
+    fn every_language_sends_a_custom_verb() {
+        let purge = Request::parse(
+            r#"{ "method": "PURGE", "path": "/cache" }"#,
+            "https://api.test",
+        )
+        .unwrap();
+        let expected = [
+            ("curl", "curl -X PURGE https://api.test/cache"),
+            (
+                "python",
+                "requests.request(\n    \"PURGE\",\n    \"https://api.test/cache\",",
+            ),
+            ("javascript", "method: \"PURGE\","),
+            (
+                "go",
+                "http.NewRequest(\"PURGE\", \"https://api.test/cache\", nil)",
+            ),
+            (
+                "rust",
+                ".request(reqwest::Method::from_bytes(b\"PURGE\")?, \"https://api.test/cache\")",
+            ),
+            ("httpie", "http PURGE https://api.test/cache"),
+            (
+                "powershell",
+                "-CustomMethod 'PURGE' `\n    -Uri 'https://api.test/cache'",
+            ),
+        ];
+        assert_eq!(expected.len(), GENERATORS.len());
+        for (id, call) in expected {
+            let code = find(id).unwrap().generate(&purge);
+            assert!(code.contains(call), "{id}:\n{code}");
+        }
+
+        let options = Request::parse(r#"{ "method": "OPTIONS", "path": "/x" }"#, "https://a");
+        assert!(rust(&options.unwrap()).contains(".request(reqwest::Method::OPTIONS, "));
+        let delete = Request::parse(
+            r#"{ "method": "delete", "path": "/x" }"#,
+            "https://api.test",
+        );
+        assert!(powershell(&delete.unwrap()).contains("-Method Delete `"));
+    }
+
+    #[test]
+    fn site_config_names_resolve_through_aliases() {
+        let names: Vec<String> = ["JS", "py", "curl", "cobol", "python"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let ids: Vec<&str> = languages(Some(&names)).iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec!["javascript", "python", "curl"]);
+        assert_eq!(languages(None).len(), GENERATORS.len());
+        assert!(languages(Some(&["none".to_string()])).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+//! What search engines need once the site has a public address: absolute
+//! canonical and `hreflang` URLs for every page, `sitemap.xml`, and
+//! `robots.txt`. Nothing here runs unless `site_url:` is set in site.md.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::output::Output;
+use crate::{LangMeta, PageMeta, PageState, SiteMeta, escape_html};
+
+/// One `<link rel="alternate" hreflang="…">` for a page.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct AlternateLink {
+    pub(crate) hreflang: String,
+    pub(crate) href: String,
+}
+
+/// `https://docs.example.com/arch` → `https://docs.example.com`. Page URLs
+/// already carry the base path, so only the origin is prepended.
+pub(crate) fn site_origin(site_url: &str) -> String {
+    let site_url = site_url.trim().trim_end_matches('/');
+    match site_url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split('/').next().unwrap_or_default();
+            format!("{scheme}://{host}")
+        }
+        None => site_url.to_string(),
+    }
+}
+
+/// Every published version of the page at `rel_slug`, one per language, plus
+/// `x-default` pointing at the default language. Empty when only one language
+/// has the page, since a lone alternate says nothing.
+pub(crate) fn alternates(site: &SiteMeta, origin: &str, rel_slug: &str) -> Vec<AlternateLink> {
+    let versions: Vec<(&LangMeta, &PageMeta)> = site
+        .langs
+        .iter()
+        .filter_map(|lang| {
+            lang.pages
+                .iter()
+                .find(|page| page.rel_slug == rel_slug && is_indexable(page))
+                .map(|page| (lang, page))
+        })
+        .collect();
+    if versions.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut links: Vec<AlternateLink> = versions
+        .iter()
+        .map(|(lang, page)| AlternateLink {
+            hreflang: lang.code.clone(),
+            href: format!("{origin}{}", page.url),
+        })
+        .collect();
+    if let Some((_, page)) = versions
+        .iter()
+        .find(|(lang, _)| lang.code == site.default_lang)
+    {
+        links.push(AlternateLink {
+            hreflang: "x-default".to_string(),
+            href: format!("{origin}{}", page.url),
+        });
+    }
+    links
+}
+
+pub(crate) fn write_sitemap(out: &Output, site: &SiteMeta, origin: &str) -> Result<()> {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\" xmlns:xhtml=\"http://www.w3.org/1999/xhtml\">\n",
+    );
+    for lang in &site.langs {
+        for page in &lang.pages {
+            if !is_indexable(page) {
+                continue;
+            }
+            xml.push_str("  <url>\n");
+            xml.push_str(&format!(
+                "    <loc>{}</loc>\n",
+                escape_xml(&format!("{origin}{}", page.url))
+            ));
+            for alt in alternates(site, origin, &page.rel_slug) {
+                xml.push_str(&format!(
+                    "    <xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+                    escape_xml(&alt.hreflang),
+                    escape_xml(&alt.href)
+                ));
+            }
+            xml.push_str("  </url>\n");
+        }
+    }
+    xml.push_str("</urlset>\n");
+    out.write("sitemap.xml", xml)
+}
+
+/// A hand-written `docs/robots.txt` is copied as-is. Otherwise `robots:` in
+/// site.md picks `allow` (the default) or `disallow`, the latter for staging
+/// copies that must stay out of search results.
+pub(crate) fn write_robots(
+    out: &Output,
+    docs_dir: &Path,
+    site: &SiteMeta,
+    robots: Option<&str>,
+    origin: Option<&str>,
+) -> Result<()> {
+    let custom = docs_dir.join("robots.txt");
+    if custom.exists() {
+        let txt =
+            fs::read(&custom).with_context(|| format!("failed to copy {}", custom.display()))?;
+        return out.write("robots.txt", txt);
+    }
+    if robots.is_none() && origin.is_none() {
+        return Ok(());
+    }
+
+    let mut txt = String::from("User-agent: *\n");
+    if robots.is_some_and(|value| value.eq_ignore_ascii_case("disallow")) {
+        txt.push_str(&format!("Disallow: {}/\n", site.base_path));
+    } else {
+        txt.push_str(&format!("Allow: {}/\n", site.base_path));
+        if let Some(origin) = origin {
+            txt.push_str(&format!(
+                "\nSitemap: {origin}{}/sitemap.xml\n",
+                site.base_path
+            ));
+        }
+    }
+    out.write("robots.txt", txt)
+}
+
+/// Drafts and hidden pages stay out, and so do fallback stand-ins, which
+/// are `noindex` copies of another language's page.
+fn is_indexable(page: &PageMeta) -> bool {
+    page.state == PageState::Published && page.fallback_from.is_none()
+}
+
+fn escape_xml(value: &str) -> String {
+    escape_html(value).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(lang: &str, slug: &str) -> PageMeta {
+        PageMeta {
+            url: crate::url_for("/docs", lang, slug),
+            rel_slug: slug.to_string(),
+            ..PageMeta::default()
+        }
+    }
+
+    #[test]
+    fn alternates_pair_pages_across_languages_by_slug() {
+        let site = SiteMeta {
+            langs: vec![
+                LangMeta {
+                    code: "en".to_string(),
+                    pages: vec![page("en", "index"), page("en", "errors")],
+                },
+                LangMeta {
+                    code: "hi".to_string(),
+                    pages: vec![page("hi", "index")],
+                },
+            ],
+            default_lang: "en".to_string(),
+            base_path: "/docs".to_string(),
+        };
+        let origin = site_origin("https://example.com/docs/");
+        assert_eq!(origin, "https://example.com");
+
+        let links = alternates(&site, &origin, "index");
+        let pairs: Vec<(&str, &str)> = links
+            .iter()
+            .map(|l| (l.hreflang.as_str(), l.href.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("en", "https://example.com/docs/en/"),
+                ("hi", "https://example.com/docs/hi/"),
+                ("x-default", "https://example.com/docs/en/"),
+            ]
+        );
+        assert!(alternates(&site, &origin, "errors").is_empty());
+    }
+}
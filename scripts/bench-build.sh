@@ -0,0 +1,141 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+ROOT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+
+PAGES="${PAGES:-${1:-2000}}"
+LANGS="${LANGS:-en hi}"
+BENCH_DIR="${BENCH_DIR:-$(mktemp -d)}"
+JOBS="${JOBS:-}"
+
+usage() {
+  cat <<'EOF'
+Generate a large docs tree and time `docsgen build` on it, once on a single
+thread and once with the default worker pool.
+
+Env vars:
+  PAGES              Pages per language (default: 2000)
+  LANGS              Space-separated language codes (default: "en hi")
+  BENCH_DIR          Where the fixture and output go (default: a new temp dir)
+  JOBS               Worker count for the parallel run (default: one per CPU)
+
+Examples:
+  ./scripts/bench-build.sh
+  ./scripts/bench-build.sh 5000
+  LANGS=en JOBS=4 ./scripts/bench-build.sh 10000
+EOF
+}
+
+if [[ "${1:-}" == "-h" || "${1:-}" == "--help" ]]; then
+  usage
+  exit 0
+fi
+
+# One page in the shape of docs/en/errors.md: prose, a table, an include, an
+# Architecture section with JSON request/response cards, and a link to the
+# next page so the link checker has work to do.
+write_page() {
+  local dir="$1" n="$2" next="$3"
+  cat >"$dir/page-$n.md" <<EOF
+---
+description: Generated page $n.
+---
+# Resource $n
+
+## Description
+
+Resource $n is created with a POST and read back by id. See
+[the next resource](page-$next.md) for the related calls.
+
+@include: shared/conventions.md
+
+### Fields
+
+| Field | Type | Notes |
+| --- | --- | --- |
+| \`id\` | string | Assigned by the server |
+| \`name\` | string | Required, up to 80 characters |
+| \`created_at\` | timestamp | RFC 3339 |
+
+## Architecture
+
+### JSON
+
+#### Request POST /v1/resources-$n
+
+\`\`\`json
+{
+  "name": "Resource $n",
+  "tags": ["bench", "generated"]
+}
+\`\`\`
+
+#### Response 201 — Created
+
+\`\`\`json
+{
+  "id": "res_$n",
+  "name": "Resource $n",
+  "created_at": "2024-01-01T00:00:00Z"
+}
+\`\`\`
+
+### Text
+
+Create the resource, keep the returned \`id\`, and read it back with a GET.
+EOF
+}
+
+DOCS_DIR="$BENCH_DIR/docs"
+rm -rf "$DOCS_DIR"
+echo "Generating $PAGES page(s) per language ($LANGS) in $DOCS_DIR"
+mkdir -p "$DOCS_DIR"
+printf 'title: Bench\n' >"$DOCS_DIR/site.md"
+for lang in $LANGS; do
+  lang_dir="$DOCS_DIR/$lang"
+  mkdir -p "$lang_dir/shared"
+  printf 'All timestamps are UTC and all ids are opaque strings.\n' >"$lang_dir/shared/conventions.md"
+  {
+    echo "[Getting started]"
+    echo "- welcome.md"
+  } >"$lang_dir/nav.md"
+  printf '# Welcome\n\nGenerated benchmark site.\n' >"$lang_dir/welcome.md"
+  group=0
+  for ((n = 0; n < PAGES; n++)); do
+    if ((n % 50 == 0)); then
+      group=$((group + 1))
+      echo "[Group $group]" >>"$lang_dir/nav.md"
+    fi
+    write_page "$lang_dir" "$n" $(((n + 1) % PAGES))
+    echo "- page-$n.md" >>"$lang_dir/nav.md"
+  done
+done
+
+echo "Building docsgen (release)"
+cargo build --release --quiet --manifest-path "$ROOT_DIR/docsgen/Cargo.toml"
+DOCSGEN="$ROOT_DIR/docsgen/target/release/docsgen"
+
+run() {
+  local label="$1"
+  shift
+  local start elapsed
+  start=$(date +%s%N)
+  "$DOCSGEN" build \
+    --docs-dir "$DOCS_DIR" \
+    --out-dir "$BENCH_DIR/public-$label" \
+    --templates-dir "$ROOT_DIR/docsgen/templates" \
+    --assets-dir "$ROOT_DIR/assets" \
+    "$@" >/dev/null
+  elapsed=$((($(date +%s%N) - start) / 1000000))
+  printf '%-10s %6d ms\n' "$label" "$elapsed"
+}
+
+run serial --jobs 1
+run parallel ${JOBS:+--jobs "$JOBS"}
+
+if cmp -s "$BENCH_DIR/public-serial/search.json" "$BENCH_DIR/public-parallel/search.json"; then
+  echo "search.json is identical in both builds"
+else
+  echo "search.json differs between the serial and parallel builds" >&2
+  exit 1
+fi